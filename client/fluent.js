@@ -1,4 +1,5 @@
-import { FluentBundle, FluentResource } from '@fluent/bundle';
+import { FluentBundle, FluentResource, FluentNumber, FluentDateTime }
+    from '@fluent/bundle';
 import { FluentParser, lineOffset, columnOffset, Resource }
     from '@fluent/syntax';
 
@@ -49,15 +50,41 @@ export function parse_messages(messages) {
     return [res, annotations];
 }
 
-export function create_bundle(locale, messages) {
+export function create_bundle(locale, messages, resources = []) {
     const bundle = new FluentBundle(locale);
     bundle.addResource(new FluentResource(messages));
+    for (const { content } of resources) {
+        bundle.addResource(new FluentResource(content));
+    }
     return bundle;
 }
 
+// Variables saved by the server are typed, e.g.
+// { "type": "number", "value": 3, "options": { "style": "percent" } }.
+function fluent_args(variables) {
+    const args = {};
+    for (const [key, val] of Object.entries(variables)) {
+        switch (val && val.type) {
+            case "string":
+                args[key] = val.value;
+                break;
+            case "number":
+                args[key] = new FluentNumber(val.value, val.options);
+                break;
+            case "date":
+                args[key] = new FluentDateTime(Date.parse(val.value), val.options);
+                break;
+            default:
+                args[key] = val;
+        }
+    }
+    return args;
+}
+
 export function format_messages(ast, bundle, variables) {
     const outputs = new Map(); 
     const errors = [];
+    const args = fluent_args(variables);
     for (const entry of ast.body) {
         if (entry.type !== "Message") {
             continue;
@@ -66,13 +93,13 @@ export function format_messages(ast, bundle, variables) {
         let id = entry.id.name;
         let message = bundle.getMessage(id);
         let value = message.value
-            ? bundle.formatPattern(message.value, variables, errors)
+            ? bundle.formatPattern(message.value, args, errors)
             : null;
         let attributes = [];
         for (let [name, value] of Object.entries(message.attributes)) {
             attributes.push({
                 id: name,
-                value: bundle.formatPattern(value, variables, errors)
+                value: bundle.formatPattern(value, args, errors)
             })
         }
 
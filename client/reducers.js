@@ -12,6 +12,7 @@ const [out, format_errors] = format_messages(ast, bundle, defaults.variables);
 const default_state = {
     locale,
     messages: defaults.messages,
+    resources: [],
     annotations,
     format_errors,
     variables: defaults.variables,
@@ -58,8 +59,8 @@ export default function reducer(state = {
         }
         case 'CHANGE_MESSAGES': {
             const { value } = action;
-            const { locale, variables } = state;
-            const bundle = create_bundle(locale, value);
+            const { locale, variables, resources } = state;
+            const bundle = create_bundle(locale, value, resources);
             const [ast, annotations] = parse_messages(value);
             const [out, format_errors] = format_messages(ast, bundle, variables);
 
@@ -75,8 +76,8 @@ export default function reducer(state = {
         }
         case 'CHANGE_LOCALE': {
             const { value: locale } = action;
-            const { ast, messages, variables } = state;
-            const bundle = create_bundle(locale, messages);
+            const { ast, messages, resources, variables } = state;
+            const bundle = create_bundle(locale, messages, resources);
             const [out, format_errors] = format_messages(ast, bundle, variables);
 
             return {
@@ -126,7 +127,8 @@ export default function reducer(state = {
         }
         case 'RECEIVE_GIST_FETCH': {
             const { gist } = action;
-            const bundle = create_bundle(locale, gist.messages);
+            const resources = gist.resources || [];
+            const bundle = create_bundle(locale, gist.messages, resources);
             const [ast, annotations] = parse_messages(gist.messages);
             const [out, format_errors] = format_messages(ast, bundle, gist.variables);
             const gist_panels = Array.isArray(gist.setup.visible) ?
@@ -142,6 +144,7 @@ export default function reducer(state = {
                 locale: gist.setup.locale,
                 dir: gist.setup.dir,
                 messages: gist.messages,
+                resources,
                 annotations,
                 variables: gist.variables,
                 variables_error: null,
@@ -158,7 +161,8 @@ export default function reducer(state = {
         }
         case 'OPEN_LINK': {
             const { body } = action;
-            const bundle = create_bundle(locale, body.messages);
+            const resources = body.resources || [];
+            const bundle = create_bundle(locale, body.messages, resources);
             const [ast, annotations] = parse_messages(body.messages);
             const [out, format_errors] = format_messages(ast, bundle, body.variables);
             const visible_panels = Array.isArray(body.setup.visible) ?
@@ -173,6 +177,7 @@ export default function reducer(state = {
                 locale: body.setup.locale,
                 dir: body.setup.dir,
                 messages: body.messages,
+                resources,
                 annotations,
                 variables: body.variables,
                 variables_error: null,
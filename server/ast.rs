@@ -0,0 +1,891 @@
+use fluent_syntax::ast;
+use fluent_syntax::parser::{self, ErrorKind, ParserError};
+use iron::{IronResult, Request, Response};
+use serde::Deserialize;
+use serde_json::json;
+use std::ops::Range;
+
+use crate::json;
+
+/// FTL sent to be parsed.
+#[derive(Debug, Deserialize)]
+struct Source {
+    messages: String,
+}
+
+/// Parse FTL, keeping the AST of a resource with errors, in which the broken
+/// entries are Junk.
+pub fn parse_resource(source: &str) -> (ast::Resource<&str>, Vec<ParserError>) {
+    match parser::parse(source) {
+        Ok(resource) => (resource, Vec::new()),
+        Err((resource, errors)) => (resource, errors),
+    }
+}
+
+/// The code, arguments and message which `@fluent/syntax` gives an error.
+pub fn describe(kind: &ErrorKind) -> (&'static str, Vec<String>, String) {
+    match kind {
+        ErrorKind::ExpectedToken(token) => (
+            "E0003",
+            vec![token.to_string()],
+            format!("Expected token: \"{}\"", token),
+        ),
+        ErrorKind::ExpectedCharRange { range } => (
+            "E0004",
+            vec![range.clone()],
+            format!("Expected a character from range: \"{}\"", range),
+        ),
+        ErrorKind::ExpectedMessageField { entry_id } => (
+            "E0005",
+            vec![entry_id.clone()],
+            format!(
+                "Expected message \"{}\" to have a value or attributes",
+                entry_id
+            ),
+        ),
+        ErrorKind::ExpectedTermField { entry_id } => (
+            "E0006",
+            vec![entry_id.clone()],
+            format!("Expected term \"-{}\" to have a value", entry_id),
+        ),
+        ErrorKind::ForbiddenCallee => (
+            "E0008",
+            vec![],
+            "The callee has to be an upper-case identifier or a term".to_string(),
+        ),
+        ErrorKind::MissingDefaultVariant => (
+            "E0010",
+            vec![],
+            "Expected one of the variants to be marked as default (*)".to_string(),
+        ),
+        ErrorKind::MissingValue => ("E0012", vec![], "Expected value".to_string()),
+        ErrorKind::ExpectedLiteral => ("E0014", vec![], "Expected literal".to_string()),
+        ErrorKind::MultipleDefaultVariants => (
+            "E0015",
+            vec![],
+            "Only one variant can be marked as default (*)".to_string(),
+        ),
+        ErrorKind::MessageReferenceAsSelector => (
+            "E0016",
+            vec![],
+            "Message references cannot be used as selectors".to_string(),
+        ),
+        ErrorKind::TermReferenceAsSelector => (
+            "E0017",
+            vec![],
+            "Terms cannot be used as selectors".to_string(),
+        ),
+        ErrorKind::MessageAttributeAsSelector => (
+            "E0018",
+            vec![],
+            "Attributes of messages cannot be used as selectors".to_string(),
+        ),
+        ErrorKind::TermAttributeAsPlaceable => (
+            "E0019",
+            vec![],
+            "Attributes of terms cannot be used as placeables".to_string(),
+        ),
+        ErrorKind::UnterminatedStringLiteral => (
+            "E0020",
+            vec![],
+            "Unterminated string expression".to_string(),
+        ),
+        ErrorKind::PositionalArgumentFollowsNamed => (
+            "E0021",
+            vec![],
+            "Positional arguments must not follow named arguments".to_string(),
+        ),
+        ErrorKind::DuplicatedNamedArgument(name) => (
+            "E0022",
+            vec![name.clone()],
+            "Named arguments must be unique".to_string(),
+        ),
+        ErrorKind::UnknownEscapeSequence(sequence) => (
+            "E0025",
+            vec![sequence.clone()],
+            format!("Unknown escape sequence: \\{}.", sequence),
+        ),
+        ErrorKind::InvalidUnicodeEscapeSequence(sequence) => (
+            "E0026",
+            vec![sequence.clone()],
+            format!("Invalid Unicode escape sequence: {}.", sequence),
+        ),
+        ErrorKind::UnbalancedClosingBrace => (
+            "E0027",
+            vec![],
+            "Unbalanced closing brace in TextElement.".to_string(),
+        ),
+        ErrorKind::ExpectedInlineExpression => {
+            ("E0028", vec![], "Expected an inline expression".to_string())
+        }
+        ErrorKind::ExpectedSimpleExpressionAsSelector => (
+            "E0029",
+            vec![],
+            "Expected simple expression as selector".to_string(),
+        ),
+    }
+}
+
+/// Call `visit` with every inline expression in `pattern`, including the
+/// ones in selectors, variants, call arguments and nested placeables.
+pub fn walk_pattern<'p, 's, F>(pattern: &'p ast::Pattern<&'s str>, visit: &mut F)
+where
+    F: FnMut(&'p ast::InlineExpression<&'s str>),
+{
+    for element in &pattern.elements {
+        if let ast::PatternElement::Placeable { expression } = element {
+            walk_expression(expression, visit);
+        }
+    }
+}
+
+fn walk_expression<'p, 's, F>(expression: &'p ast::Expression<&'s str>, visit: &mut F)
+where
+    F: FnMut(&'p ast::InlineExpression<&'s str>),
+{
+    match expression {
+        ast::Expression::Select { selector, variants } => {
+            walk_inline_expression(selector, visit);
+            for variant in variants {
+                walk_pattern(&variant.value, visit);
+            }
+        }
+        ast::Expression::Inline(expression) => walk_inline_expression(expression, visit),
+    }
+}
+
+fn walk_inline_expression<'p, 's, F>(expression: &'p ast::InlineExpression<&'s str>, visit: &mut F)
+where
+    F: FnMut(&'p ast::InlineExpression<&'s str>),
+{
+    visit(expression);
+    match expression {
+        ast::InlineExpression::FunctionReference { arguments, .. }
+        | ast::InlineExpression::TermReference {
+            arguments: Some(arguments),
+            ..
+        } => {
+            for argument in &arguments.positional {
+                walk_inline_expression(argument, visit);
+            }
+            for argument in &arguments.named {
+                walk_inline_expression(&argument.value, visit);
+            }
+        }
+        ast::InlineExpression::Placeable { expression } => walk_expression(expression, visit),
+        _ => {}
+    }
+}
+
+/// Call `visit` with every expression in `pattern`: its placeables, and the
+/// placeables nested in them. For a select expression, `visit` returns the
+/// variants whose patterns are walked next, so that a caller can follow all
+/// of them or only the one which was selected.
+pub fn walk_expressions<'p, 's, F>(pattern: &'p ast::Pattern<&'s str>, visit: &mut F)
+where
+    F: FnMut(&'p ast::Expression<&'s str>) -> &'p [ast::Variant<&'s str>],
+{
+    for element in &pattern.elements {
+        if let ast::PatternElement::Placeable { expression } = element {
+            walk_nested_expression(expression, visit);
+        }
+    }
+}
+
+fn walk_nested_expression<'p, 's, F>(expression: &'p ast::Expression<&'s str>, visit: &mut F)
+where
+    F: FnMut(&'p ast::Expression<&'s str>) -> &'p [ast::Variant<&'s str>],
+{
+    let variants = visit(expression);
+    match expression {
+        ast::Expression::Select { selector, .. } => walk_placeables(selector, visit),
+        ast::Expression::Inline(expression) => walk_placeables(expression, visit),
+    }
+    for variant in variants {
+        walk_expressions(&variant.value, visit);
+    }
+}
+
+/// Walk the placeables in `expression`, or in the arguments of a call.
+fn walk_placeables<'p, 's, F>(expression: &'p ast::InlineExpression<&'s str>, visit: &mut F)
+where
+    F: FnMut(&'p ast::Expression<&'s str>) -> &'p [ast::Variant<&'s str>],
+{
+    match expression {
+        ast::InlineExpression::FunctionReference { arguments, .. }
+        | ast::InlineExpression::TermReference {
+            arguments: Some(arguments),
+            ..
+        } => {
+            for argument in &arguments.positional {
+                walk_placeables(argument, visit);
+            }
+            for argument in &arguments.named {
+                walk_placeables(&argument.value, visit);
+            }
+        }
+        ast::InlineExpression::Placeable { expression } => {
+            walk_nested_expression(expression, visit)
+        }
+        _ => {}
+    }
+}
+
+fn is_blank(byte: u8) -> bool {
+    byte == b' ' || byte == b'\n' || byte == b'\r'
+}
+
+/// Junk as `@fluent/syntax` delimits it.
+#[derive(Debug)]
+pub struct Junk {
+    pub range: Range<usize>,
+    /// The error which made the entry Junk, and the offset it's reported at.
+    pub error: Option<(ErrorKind, usize)>,
+}
+
+/// Every Junk in `source`, in order.
+pub fn find_junk(source: &str) -> Vec<Junk> {
+    Spans::new(source).find_junk(0..source.len())
+}
+
+/// A node of the AST as JSON, along with the bytes of the source it spans.
+type Node = (serde_json::Value, Range<usize>);
+
+/// Turns the AST into JSON shaped like the one `@fluent/syntax` produces for
+/// the client's AST panel.
+///
+/// The Rust parser doesn't record spans, but every name and text in its AST
+/// is a slice of the source, so spans are found from where those slices lie
+/// and from the delimiters around them.
+pub struct Spans<'s> {
+    source: &'s str,
+    /// The UTF-16 offset of every byte offset, since the client counts
+    /// positions in UTF-16 code units.
+    utf16: Vec<usize>,
+}
+
+impl<'s> Spans<'s> {
+    pub fn new(source: &'s str) -> Self {
+        let mut utf16 = Vec::with_capacity(source.len() + 1);
+        let mut offset = 0;
+        for c in source.chars() {
+            for _ in 0..c.len_utf8() {
+                utf16.push(offset);
+            }
+            offset += c.len_utf16();
+        }
+        utf16.push(offset);
+        Spans { source, utf16 }
+    }
+
+    /// The byte offset at which `slice`, borrowed from the source, starts.
+    pub fn start(&self, slice: &str) -> usize {
+        (slice.as_ptr() as usize)
+            .saturating_sub(self.source.as_ptr() as usize)
+            .min(self.source.len())
+    }
+
+    /// The byte range of `slice`, borrowed from the source.
+    pub fn range(&self, slice: &str) -> Range<usize> {
+        let start = self.start(slice);
+        start..(start + slice.len()).min(self.source.len())
+    }
+
+    /// The offset of the first byte from `pos` on which isn't one of `bytes`.
+    fn skip(&self, pos: usize, bytes: &[u8]) -> usize {
+        pos + self.source.as_bytes()[pos..]
+            .iter()
+            .take_while(|byte| bytes.contains(byte))
+            .count()
+    }
+
+    /// The offset of `delimiter`, if it comes right before `pos`, blanks
+    /// aside.
+    fn opening(&self, pos: usize, delimiter: u8) -> usize {
+        let bytes = self.source.as_bytes();
+        let mut start = pos;
+        while start > 0 && is_blank(bytes[start - 1]) {
+            start -= 1;
+        }
+        if start > 0 && bytes[start - 1] == delimiter {
+            start - 1
+        } else {
+            pos
+        }
+    }
+
+    /// The offset after `delimiter`, if it comes right after `pos`, blanks
+    /// aside.
+    fn closing(&self, pos: usize, delimiter: u8) -> usize {
+        let bytes = self.source.as_bytes();
+        let mut end = pos;
+        while end < bytes.len() && is_blank(bytes[end]) {
+            end += 1;
+        }
+        if end < bytes.len() && bytes[end] == delimiter {
+            end + 1
+        } else {
+            pos
+        }
+    }
+
+    pub fn span(&self, range: &Range<usize>) -> serde_json::Value {
+        let len = self.source.len();
+        json!({
+            "type": "Span",
+            "start": self.utf16[range.start.min(len)],
+            "end": self.utf16[range.end.min(len)],
+        })
+    }
+
+    fn node(&self, mut value: serde_json::Value, range: Range<usize>) -> Node {
+        value["span"] = self.span(&range);
+        (value, range)
+    }
+
+    pub fn resource(&self, resource: &ast::Resource<&str>, errors: &[ParserError]) -> Node {
+        let body = self.body(resource, errors, 0);
+        self.node(
+            json!({ "type": "Resource", "body": body }),
+            0..self.source.len(),
+        )
+    }
+
+    /// The entries of a resource parsed from the source from `offset` on, to
+    /// which the positions in `errors` are relative.
+    fn body(
+        &self,
+        resource: &ast::Resource<&str>,
+        errors: &[ParserError],
+        offset: usize,
+    ) -> Vec<serde_json::Value> {
+        let mut body = Vec::new();
+        for entry in &resource.body {
+            match entry {
+                ast::Entry::Message(message) => body.push(
+                    self.message(
+                        "Message",
+                        &message.id,
+                        message.value.as_ref(),
+                        &message.attributes,
+                        message.comment.as_ref(),
+                    )
+                    .0,
+                ),
+                ast::Entry::Term(term) => body.push(
+                    self.message(
+                        "Term",
+                        &term.id,
+                        Some(&term.value),
+                        &term.attributes,
+                        term.comment.as_ref(),
+                    )
+                    .0,
+                ),
+                ast::Entry::Comment(comment) => body.push(self.comment("Comment", comment).0),
+                ast::Entry::GroupComment(comment) => {
+                    body.push(self.comment("GroupComment", comment).0)
+                }
+                ast::Entry::ResourceComment(comment) => {
+                    body.push(self.comment("ResourceComment", comment).0)
+                }
+                ast::Entry::Junk { content } => {
+                    let (junk, rest) = self.split_junk(self.range(content), errors, offset);
+                    body.push(self.junk(&junk).0);
+                    if !rest.is_empty() {
+                        let (resource, errors) = parse_resource(&self.source[rest.clone()]);
+                        body.extend(self.body(&resource, &errors, rest.start));
+                    }
+                }
+            }
+        }
+        body
+    }
+
+    /// The Rust parser looks for the next entry after the position of the
+    /// error, while `@fluent/syntax` already looks on the line of the error.
+    /// When that line starts an entry, the Junk ends before it, and the rest
+    /// of the range is returned to be parsed again.
+    fn split_junk(
+        &self,
+        range: Range<usize>,
+        errors: &[ParserError],
+        offset: usize,
+    ) -> (Junk, Range<usize>) {
+        let error = errors.iter().find(|error| {
+            error
+                .slice
+                .as_ref()
+                .map(|slice| slice.start + offset..slice.end + offset)
+                == Some(range.clone())
+        });
+        let pos = error.map_or(range.start, |error| self.position(error, offset));
+        let bytes = self.source.as_bytes();
+        let line = bytes[..(pos + 1).min(bytes.len())]
+            .iter()
+            .rposition(|&byte| byte == b'\n')
+            .map_or(0, |newline| newline + 1);
+        let starts_entry = match bytes.get(line) {
+            Some(&byte) => byte.is_ascii_alphabetic() || byte == b'-' || byte == b'#',
+            None => false,
+        };
+        let end = if line > range.start && line < range.end && starts_entry {
+            line
+        } else {
+            range.end
+        };
+        let junk = Junk {
+            range: range.start..end,
+            error: error.map(|error| (error.kind.clone(), pos.min(end))),
+        };
+        (junk, end..range.end)
+    }
+
+    fn find_junk(&self, range: Range<usize>) -> Vec<Junk> {
+        let (resource, errors) = parse_resource(&self.source[range.clone()]);
+        let mut found = Vec::new();
+        for entry in &resource.body {
+            if let ast::Entry::Junk { content } = entry {
+                let (junk, rest) = self.split_junk(self.range(content), &errors, range.start);
+                found.push(junk);
+                if !rest.is_empty() {
+                    found.extend(self.find_junk(rest));
+                }
+            }
+        }
+        found
+    }
+
+    /// Where `@fluent/syntax` reports an error, as an offset in the source.
+    ///
+    /// The Rust parser reports entries without a value at their start, but
+    /// `@fluent/syntax` does so after their `=`.
+    fn position(&self, error: &ParserError, offset: usize) -> usize {
+        let mut pos = (error.pos.start + offset).min(self.source.len());
+        while !self.source.is_char_boundary(pos) {
+            pos -= 1;
+        }
+        match error.kind {
+            ErrorKind::ExpectedMessageField { .. } | ErrorKind::ExpectedTermField { .. } => self
+                .source[pos..]
+                .find('=')
+                .map_or(pos, |equals| pos + equals + 1),
+            _ => pos,
+        }
+    }
+
+    fn junk(&self, junk: &Junk) -> Node {
+        let annotations: Vec<_> = junk
+            .error
+            .iter()
+            .map(|(kind, pos)| {
+                let (code, arguments, message) = describe(kind);
+                json!({
+                    "type": "Annotation",
+                    "code": code,
+                    "arguments": arguments,
+                    "message": message,
+                    "span": self.span(&(*pos..*pos)),
+                })
+            })
+            .collect();
+        self.node(
+            json!({
+                "type": "Junk",
+                "annotations": annotations,
+                "content": &self.source[junk.range.clone()],
+            }),
+            junk.range.clone(),
+        )
+    }
+
+    /// Messages and terms only differ in their type and the `-` before a
+    /// term's id.
+    fn message(
+        &self,
+        kind: &str,
+        id: &ast::Identifier<&str>,
+        value: Option<&ast::Pattern<&str>>,
+        attributes: &[ast::Attribute<&str>],
+        comment: Option<&ast::Comment<&str>>,
+    ) -> Node {
+        let (id, id_range) = self.identifier(id);
+        let mut start = if kind == "Term" {
+            id_range.start.saturating_sub(1)
+        } else {
+            id_range.start
+        };
+        let mut end = id_range.end;
+        let value = value.map(|value| {
+            let (value, range) = self.pattern(value);
+            end = end.max(range.end);
+            value
+        });
+        let attributes: Vec<_> = attributes
+            .iter()
+            .map(|attribute| {
+                let (attribute, range) = self.attribute(attribute);
+                end = end.max(range.end);
+                attribute
+            })
+            .collect();
+        let comment = comment.map(|comment| {
+            let (comment, range) = self.comment("Comment", comment);
+            start = start.min(range.start);
+            comment
+        });
+        self.node(
+            json!({
+                "type": kind,
+                "id": id,
+                "value": value,
+                "attributes": attributes,
+                "comment": comment,
+            }),
+            start..end,
+        )
+    }
+
+    fn comment(&self, kind: &str, comment: &ast::Comment<&str>) -> Node {
+        let first = comment.content.first().map_or(0, |line| self.start(line));
+        let start = self.source[..first].rfind('\n').map_or(0, |pos| pos + 1);
+        let end = comment
+            .content
+            .last()
+            .map_or(first, |line| self.range(line).end);
+        self.node(
+            json!({ "type": kind, "content": comment.content.join("\n") }),
+            start..end,
+        )
+    }
+
+    fn identifier(&self, id: &ast::Identifier<&str>) -> Node {
+        self.node(
+            json!({ "type": "Identifier", "name": id.name }),
+            self.range(id.name),
+        )
+    }
+
+    fn attribute(&self, attribute: &ast::Attribute<&str>) -> Node {
+        let (id, id_range) = self.identifier(&attribute.id);
+        let (value, value_range) = self.pattern(&attribute.value);
+        self.node(
+            json!({ "type": "Attribute", "id": id, "value": value }),
+            id_range.start.saturating_sub(1)..value_range.end,
+        )
+    }
+
+    /// Text on consecutive lines is a single TextElement in `@fluent/syntax`
+    /// but one per line in the Rust parser, so adjacent ones are joined.
+    fn pattern(&self, pattern: &ast::Pattern<&str>) -> Node {
+        let mut elements = Vec::new();
+        let mut text: Option<(String, Range<usize>)> = None;
+        for element in &pattern.elements {
+            match element {
+                ast::PatternElement::TextElement { value } => {
+                    let range = self.range(value);
+                    text = Some(match text.take() {
+                        Some((mut joined, joined_range)) => {
+                            joined.push_str(value);
+                            (joined, joined_range.start..range.end)
+                        }
+                        None => (value.to_string(), range),
+                    });
+                }
+                ast::PatternElement::Placeable { expression } => {
+                    if let Some((value, range)) = text.take() {
+                        elements.push(self.text_element(value, range));
+                    }
+                    elements.push(self.placeable(expression));
+                }
+            }
+        }
+        if let Some((value, range)) = text.take() {
+            // The trailing blanks trimmed from the value are still part of
+            // the element.
+            let end = self.skip(range.end, b" ");
+            elements.push(self.text_element(value, range.start..end));
+        }
+        let first = elements.first().map_or(0, |(_, range)| range.start);
+        let end = elements
+            .last()
+            .map_or(first, |(_, range)| self.skip(range.end, b" "));
+        // A pattern which starts on its own line starts with its indent.
+        let indent = self.source[..first].trim_end_matches(' ').len();
+        let start = if self.source[..indent].ends_with('\n') {
+            indent
+        } else {
+            first
+        };
+        // So does its first text, if it's indented deeper than the rest.
+        if let Some((element, range)) = elements.first_mut() {
+            if element["type"] == "TextElement" && self.source[range.clone()].starts_with(' ') {
+                range.start = start;
+                element["span"] = self.span(range);
+            }
+        }
+        let elements: Vec<_> = elements.into_iter().map(|(element, _)| element).collect();
+        self.node(
+            json!({ "type": "Pattern", "elements": elements }),
+            start..end,
+        )
+    }
+
+    fn text_element(&self, value: String, range: Range<usize>) -> Node {
+        self.node(json!({ "type": "TextElement", "value": value }), range)
+    }
+
+    fn placeable(&self, expression: &ast::Expression<&str>) -> Node {
+        let (expression, range) = self.expression(expression);
+        self.node(
+            json!({ "type": "Placeable", "expression": expression }),
+            self.opening(range.start, b'{')..self.closing(range.end, b'}'),
+        )
+    }
+
+    fn expression(&self, expression: &ast::Expression<&str>) -> Node {
+        match expression {
+            ast::Expression::Select { selector, variants } => {
+                let (selector, selector_range) = self.inline_expression(selector);
+                let mut end = selector_range.end;
+                let variants: Vec<_> = variants
+                    .iter()
+                    .map(|variant| {
+                        let (variant, range) = self.variant(variant);
+                        end = end.max(range.end);
+                        variant
+                    })
+                    .collect();
+                self.node(
+                    json!({
+                        "type": "SelectExpression",
+                        "selector": selector,
+                        "variants": variants,
+                    }),
+                    // The variants are followed by blank lines up to the `}`.
+                    selector_range.start..self.skip(end, b" \r\n"),
+                )
+            }
+            ast::Expression::Inline(expression) => self.inline_expression(expression),
+        }
+    }
+
+    fn variant(&self, variant: &ast::Variant<&str>) -> Node {
+        let (key, key_range) = match variant.key {
+            ast::VariantKey::Identifier { name } => (
+                json!({ "type": "Identifier", "name": name }),
+                self.range(name),
+            ),
+            ast::VariantKey::NumberLiteral { value } => (
+                json!({ "type": "NumberLiteral", "value": value }),
+                self.range(value),
+            ),
+        };
+        let (key, key_range) = self.node(key, key_range);
+        let (value, value_range) = self.pattern(&variant.value);
+        let mut start = self.opening(key_range.start, b'[');
+        if variant.default {
+            start = self.opening(start, b'*');
+        }
+        self.node(
+            json!({
+                "type": "Variant",
+                "key": key,
+                "value": value,
+                "default": variant.default,
+            }),
+            start..value_range.end,
+        )
+    }
+
+    pub fn inline_expression(&self, expression: &ast::InlineExpression<&str>) -> Node {
+        match expression {
+            ast::InlineExpression::StringLiteral { value } => {
+                let range = self.range(value);
+                self.node(
+                    json!({ "type": "StringLiteral", "value": value }),
+                    range.start.saturating_sub(1)..(range.end + 1).min(self.source.len()),
+                )
+            }
+            ast::InlineExpression::NumberLiteral { value } => self.node(
+                json!({ "type": "NumberLiteral", "value": value }),
+                self.range(value),
+            ),
+            ast::InlineExpression::FunctionReference { id, arguments } => {
+                let (id, id_range) = self.identifier(id);
+                let (arguments, arguments_range) = self.call_arguments(arguments, id_range.end);
+                self.node(
+                    json!({
+                        "type": "FunctionReference",
+                        "id": id,
+                        "arguments": arguments,
+                    }),
+                    id_range.start..arguments_range.end,
+                )
+            }
+            ast::InlineExpression::MessageReference { id, attribute } => {
+                let (id, id_range) = self.identifier(id);
+                let mut end = id_range.end;
+                let attribute = attribute.as_ref().map(|attribute| {
+                    let (attribute, range) = self.identifier(attribute);
+                    end = range.end;
+                    attribute
+                });
+                self.node(
+                    json!({
+                        "type": "MessageReference",
+                        "id": id,
+                        "attribute": attribute,
+                    }),
+                    id_range.start..end,
+                )
+            }
+            ast::InlineExpression::TermReference {
+                id,
+                attribute,
+                arguments,
+            } => {
+                let (id, id_range) = self.identifier(id);
+                let mut end = id_range.end;
+                let attribute = attribute.as_ref().map(|attribute| {
+                    let (attribute, range) = self.identifier(attribute);
+                    end = range.end;
+                    attribute
+                });
+                let arguments = arguments.as_ref().map(|arguments| {
+                    let (arguments, range) = self.call_arguments(arguments, end);
+                    end = range.end;
+                    arguments
+                });
+                self.node(
+                    json!({
+                        "type": "TermReference",
+                        "id": id,
+                        "attribute": attribute,
+                        "arguments": arguments,
+                    }),
+                    id_range.start.saturating_sub(1)..end,
+                )
+            }
+            ast::InlineExpression::VariableReference { id } => {
+                let (id, id_range) = self.identifier(id);
+                self.node(
+                    json!({ "type": "VariableReference", "id": id }),
+                    id_range.start.saturating_sub(1)..id_range.end,
+                )
+            }
+            ast::InlineExpression::Placeable { expression } => self.placeable(expression),
+        }
+    }
+
+    /// Call arguments start with the `(` which follows the callee at
+    /// `callee_end`.
+    fn call_arguments(&self, arguments: &ast::CallArguments<&str>, callee_end: usize) -> Node {
+        let open = self.closing(callee_end, b'(');
+        let mut end = open;
+        let positional: Vec<_> = arguments
+            .positional
+            .iter()
+            .map(|argument| {
+                let (argument, range) = self.inline_expression(argument);
+                end = end.max(range.end);
+                argument
+            })
+            .collect();
+        let named: Vec<_> = arguments
+            .named
+            .iter()
+            .map(|argument| {
+                let (name, name_range) = self.identifier(&argument.name);
+                let (value, value_range) = self.inline_expression(&argument.value);
+                end = end.max(value_range.end);
+                self.node(
+                    json!({ "type": "NamedArgument", "name": name, "value": value }),
+                    name_range.start..value_range.end,
+                )
+                .0
+            })
+            .collect();
+        self.node(
+            json!({
+                "type": "CallArguments",
+                "positional": positional,
+                "named": named,
+            }),
+            open.saturating_sub(1)..self.closing(end, b')'),
+        )
+    }
+}
+
+/// Parse the FTL in the request the way the client's AST panel shows it.
+pub fn parse(req: &mut Request) -> IronResult<Response> {
+    let source: Source = match json::read(req) {
+        Ok(source) => source,
+        Err(err) => return json::error(err.status(), err),
+    };
+    let (resource, errors) = parse_resource(&source.messages);
+    let spans = Spans::new(&source.messages);
+    json::respond(spans.resource(&resource, &errors).0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The fixtures hold what `@fluent/syntax` parses each FTL file into,
+    /// spans included.
+    fn assert_parses_like_fluent_syntax(source: &str, expected: &str) {
+        let (resource, errors) = parse_resource(source);
+        let (actual, _) = Spans::new(source).resource(&resource, &errors);
+        let expected: serde_json::Value = serde_json::from_str(expected).unwrap();
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn parses_default_messages() {
+        assert_parses_like_fluent_syntax(
+            include_str!("fixtures/ast/defaults.ftl"),
+            include_str!("fixtures/ast/defaults.json"),
+        );
+    }
+
+    #[test]
+    fn parses_comments() {
+        assert_parses_like_fluent_syntax(
+            include_str!("fixtures/ast/comments.ftl"),
+            include_str!("fixtures/ast/comments.json"),
+        );
+    }
+
+    #[test]
+    fn parses_terms_with_arguments_and_attributes() {
+        assert_parses_like_fluent_syntax(
+            include_str!("fixtures/ast/terms.ftl"),
+            include_str!("fixtures/ast/terms.json"),
+        );
+    }
+
+    #[test]
+    fn parses_junk_up_to_the_next_entry() {
+        assert_parses_like_fluent_syntax(
+            include_str!("fixtures/ast/junk.ftl"),
+            include_str!("fixtures/ast/junk.json"),
+        );
+    }
+
+    #[test]
+    fn parses_crlf_line_endings() {
+        assert_parses_like_fluent_syntax(
+            include_str!("fixtures/ast/crlf.ftl"),
+            include_str!("fixtures/ast/crlf.json"),
+        );
+    }
+
+    #[test]
+    fn counts_spans_in_utf16() {
+        assert_parses_like_fluent_syntax(
+            include_str!("fixtures/ast/non-bmp.ftl"),
+            include_str!("fixtures/ast/non-bmp.json"),
+        );
+    }
+}
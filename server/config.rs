@@ -0,0 +1,39 @@
+use std::env;
+
+use crate::store::generate_id;
+
+/// Settings read from the environment at startup.
+#[derive(Clone, Debug)]
+pub struct Config {
+    /// The largest request body accepted when saving a playground, in bytes.
+    pub max_payload_size: u64,
+    /// The largest gist file which will be loaded into a playground, in bytes.
+    pub max_file_size: u64,
+    /// The key used to derive edit tokens for playgrounds.
+    pub edit_token_secret: String,
+    /// A credential which authorizes changes to any playground.
+    pub admin_token: Option<String>,
+}
+
+impl Config {
+    pub fn from_env() -> Self {
+        Config {
+            max_payload_size: size_from_env("MAX_PAYLOAD_SIZE", 256 * 1024),
+            max_file_size: size_from_env("MAX_FILE_SIZE", 1024 * 1024),
+            edit_token_secret: env::var("EDIT_TOKEN_SECRET").unwrap_or_else(|_| {
+                eprintln!("EDIT_TOKEN_SECRET not set, edit tokens won't survive a restart");
+                generate_id() + &generate_id()
+            }),
+            admin_token: env::var("ADMIN_TOKEN").ok(),
+        }
+    }
+}
+
+fn size_from_env(name: &str, default: u64) -> u64 {
+    match env::var(name) {
+        Ok(size) => size
+            .parse()
+            .unwrap_or_else(|_| panic!("Unable to parse {} into a number", name)),
+        Err(_) => default,
+    }
+}
@@ -0,0 +1,158 @@
+use fluent_syntax::ast;
+use iron::{IronResult, Request, Response};
+use serde::Serialize;
+use std::ops::Range;
+
+use crate::ast::{parse_resource, walk_expressions, Spans};
+use crate::errors::Error;
+use crate::explain::{explain, key_name, message_patterns};
+use crate::format::{create_bundle, fluent_args};
+use crate::json;
+use crate::matrix::MatrixRequest;
+
+#[derive(Debug, Serialize)]
+pub struct VariantCoverage {
+    pub key: String,
+    pub default: bool,
+    /// Where the variant's key is, as in the AST.
+    pub span: serde_json::Value,
+    /// How many of the variable sets used the variant.
+    pub reached: usize,
+}
+
+/// The variants of a select expression in the messages, and how often each
+/// was used.
+#[derive(Debug, Serialize)]
+pub struct SelectCoverage {
+    pub message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub attribute: Option<String>,
+    /// The selector as written in the FTL.
+    pub expression: String,
+    pub span: serde_json::Value,
+    #[serde(skip)]
+    range: Range<usize>,
+    pub variants: Vec<VariantCoverage>,
+}
+
+#[derive(Debug, Serialize)]
+struct Coverage {
+    /// How many variable sets the messages were formatted with.
+    sets: usize,
+    /// How many variants there are, and how many of them were never used.
+    variants: usize,
+    unreached: usize,
+    selects: Vec<SelectCoverage>,
+}
+
+/// Every select expression in the messages of `resource`, including those
+/// nested in variants.
+fn collect_selects(source: &str, resource: &ast::Resource<&str>) -> Vec<SelectCoverage> {
+    let spans = Spans::new(source);
+    let mut selects = Vec::new();
+    for (message, attribute, pattern) in message_patterns(resource) {
+        walk_expressions(pattern, &mut |expression| {
+            let (selector, variants) = match expression {
+                ast::Expression::Select { selector, variants } => (selector, variants),
+                ast::Expression::Inline(_) => return &[],
+            };
+            let (_, range) = spans.inline_expression(selector);
+            selects.push(SelectCoverage {
+                message: message.to_string(),
+                attribute: attribute.map(str::to_string),
+                expression: source[range.clone()].to_string(),
+                span: spans.span(&range),
+                range,
+                variants: variants
+                    .iter()
+                    .map(|variant| {
+                        let key = match variant.key {
+                            ast::VariantKey::Identifier { name } => name,
+                            ast::VariantKey::NumberLiteral { value } => value,
+                        };
+                        VariantCoverage {
+                            key: key_name(&variant.key),
+                            default: variant.default,
+                            span: spans.span(&spans.range(key)),
+                            reached: 0,
+                        }
+                    })
+                    .collect(),
+            });
+            variants
+        });
+    }
+    selects
+}
+
+fn coverage(request: MatrixRequest) -> Result<Coverage, Error> {
+    let (request, combinations) = request.combinations()?;
+    let bundle = create_bundle(request.langid()?, request.sources());
+    let (resource, _) = parse_resource(&request.messages);
+    let mut selects = collect_selects(&request.messages, &resource);
+
+    for variables in &combinations {
+        let selections = explain(
+            &request.messages,
+            &resource,
+            &bundle,
+            &fluent_args(variables),
+            request.langid()?,
+        );
+        for selection in selections {
+            let select = selects
+                .iter_mut()
+                .find(|select| select.range == selection.range);
+            if let (Some(select), Some(index)) = (select, selection.variant) {
+                select.variants[index].reached += 1;
+            }
+        }
+    }
+
+    let all_variants = selects.iter().flat_map(|select| &select.variants);
+    Ok(Coverage {
+        sets: combinations.len(),
+        variants: all_variants.clone().count(),
+        unreached: all_variants.filter(|variant| variant.reached == 0).count(),
+        selects,
+    })
+}
+
+/// Report which variants of the messages the request's variable sets reach.
+pub fn post(req: &mut Request) -> IronResult<Response> {
+    match json::read(req).and_then(coverage) {
+        Ok(coverage) => json::respond(coverage),
+        Err(err) => json::error(err.status(), err),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn reached(request: serde_json::Value) -> Vec<(String, usize)> {
+        let request: MatrixRequest = serde_json::from_value(request).unwrap();
+        let coverage = coverage(request).unwrap();
+        coverage.selects[0]
+            .variants
+            .iter()
+            .map(|variant| (variant.key.clone(), variant.reached))
+            .collect()
+    }
+
+    #[test]
+    fn counts_variants_reached_through_number_selectors() {
+        let reached = reached(json!({
+            "messages": "photos = { NUMBER($n) ->\n    [one] one\n    [few] few\n    [many] many\n   *[other] other\n}\n",
+            "locale": "pl",
+            "matrix": {"n": [1, 2, 5, 22, 1.5]},
+        }));
+        let expected = vec![("one", 1), ("few", 2), ("many", 1), ("other", 1)];
+        let expected: Vec<(String, usize)> = expected
+            .into_iter()
+            .map(|(key, count)| (key.to_string(), count))
+            .collect();
+        assert_eq!(reached, expected);
+    }
+}
@@ -0,0 +1,136 @@
+use iron::{IronResult, Request, Response};
+use serde::{Deserialize, Serialize};
+
+use crate::ast::{describe, find_junk, Junk};
+use crate::json;
+
+/// FTL sent to be checked.
+#[derive(Debug, Deserialize)]
+struct Source {
+    messages: String,
+}
+
+/// A syntax error, laid out the way the client's console shows it.
+#[derive(Debug, Serialize)]
+pub struct Diagnostic {
+    pub code: &'static str,
+    pub message: String,
+    /// The line of the error, counted from 1.
+    pub line: usize,
+    /// The column of the error in characters, counted from 1.
+    pub column: usize,
+    /// The lines of the Junk up to and including the one with the error.
+    pub head: String,
+    /// The rest of the Junk.
+    pub tail: String,
+}
+
+/// Describe a syntax error like `annotation_display` in the client.
+fn diagnostic(source: &str, junk: &Junk) -> Option<Diagnostic> {
+    let (kind, pos) = junk.error.as_ref()?;
+    let (code, _, message) = describe(kind);
+    let pos = *pos;
+
+    let line_start = source[..pos].rfind('\n').map_or(0, |newline| newline + 1);
+    let line = source[..pos].matches('\n').count();
+    let junk_line = source[..junk.range.start].matches('\n').count();
+    let head_len = line + 1 - junk_line;
+    let lines: Vec<&str> = source[junk.range.clone()].split('\n').collect();
+    let head_len = head_len.min(lines.len());
+
+    Some(Diagnostic {
+        code,
+        message,
+        line: line + 1,
+        column: source[line_start..pos].chars().count() + 1,
+        head: lines[..head_len].join("\n") + "\n",
+        tail: lines[head_len..].join("\n"),
+    })
+}
+
+/// Every syntax error in `source`, in the order they appear.
+pub fn diagnose(source: &str) -> Vec<Diagnostic> {
+    find_junk(source)
+        .iter()
+        .filter_map(|junk| diagnostic(source, junk))
+        .collect()
+}
+
+pub fn check(req: &mut Request) -> IronResult<Response> {
+    match json::read::<Source>(req) {
+        Ok(source) => json::respond(diagnose(&source.messages)),
+        Err(err) => json::error(err.status(), err),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn diagnose_one(source: &str) -> Diagnostic {
+        let mut diagnostics = diagnose(source);
+        assert_eq!(diagnostics.len(), 1, "{:?}", diagnostics);
+        diagnostics.remove(0)
+    }
+
+    #[test]
+    fn reports_line_and_column() {
+        let diagnostic = diagnose_one("hello = Hello\nbye = }\n");
+        assert_eq!(diagnostic.code, "E0027");
+        assert_eq!((diagnostic.line, diagnostic.column), (2, 7));
+        assert_eq!(diagnostic.head, "bye = }\n");
+        assert_eq!(diagnostic.tail, "");
+    }
+
+    #[test]
+    fn counts_columns_in_characters() {
+        let diagnostic = diagnose_one("emoji = 😀 é }\n");
+        assert_eq!((diagnostic.line, diagnostic.column), (1, 13));
+    }
+
+    #[test]
+    fn splits_multiline_junk_at_the_line_of_the_error() {
+        let source = "hello = Hello\nbroken = { $n ->\n    [one] { $n\n   *[other] Other\n}\n";
+        let diagnostic = diagnose_one(source);
+        assert_eq!(diagnostic.code, "E0003");
+        assert_eq!((diagnostic.line, diagnostic.column), (4, 4));
+        assert_eq!(
+            diagnostic.head,
+            "broken = { $n ->\n    [one] { $n\n   *[other] Other\n"
+        );
+        assert_eq!(diagnostic.tail, "}\n");
+    }
+
+    #[test]
+    fn splits_junk_with_multibyte_text() {
+        let source = "cafe = Café {\n    ünïcödé\n  ẞ\nok = Ok\n";
+        let diagnostic = diagnose_one(source);
+        assert_eq!(diagnostic.code, "E0028");
+        assert_eq!((diagnostic.line, diagnostic.column), (2, 5));
+        assert_eq!(diagnostic.head, "cafe = Café {\n    ünïcödé\n");
+        assert_eq!(diagnostic.tail, "  ẞ\n");
+    }
+
+    #[test]
+    fn reports_entries_without_value_after_their_equals_sign() {
+        let diagnostic = diagnose_one("hello =\nbye = Bye\n");
+        assert_eq!(diagnostic.code, "E0005");
+        assert_eq!((diagnostic.line, diagnostic.column), (1, 8));
+        assert_eq!(diagnostic.head, "hello =\n");
+        assert_eq!(diagnostic.tail, "");
+    }
+
+    #[test]
+    fn ends_junk_before_an_entry_on_the_line_of_the_error() {
+        let diagnostics = diagnose("broken = {\nok = }\n");
+        let lines: Vec<_> = diagnostics
+            .iter()
+            .map(|diagnostic| (diagnostic.code, diagnostic.line, diagnostic.column))
+            .collect();
+        assert_eq!(lines, [("E0003", 2, 1), ("E0027", 2, 6)]);
+        // Like the client, the head runs up to the line of the error, which
+        // here is the empty line after the Junk.
+        assert_eq!(diagnostics[0].head, "broken = {\n\n");
+        assert_eq!(diagnostics[0].tail, "");
+    }
+}
@@ -1,16 +1,39 @@
+use iron::status;
 use std::fmt;
 
 #[derive(Debug)]
 pub enum Error {
     Runtime,
     NotFound,
+    RevisionNotFound,
+    Unauthorized,
     Fetching,
     Creating,
+    Updating,
+    Deleting,
     ReadingRequest,
+    PayloadTooLarge(u64),
+    InvalidPayload(String),
+    InvalidRequest(String),
     Deserializing,
     Serializing,
     MissingFile(String),
     EmptyFile(String),
+    FileTooLarge(String),
+}
+
+impl Error {
+    pub fn status(&self) -> status::Status {
+        match self {
+            Error::Runtime => status::ServiceUnavailable,
+            Error::NotFound | Error::RevisionNotFound => status::NotFound,
+            Error::Unauthorized => status::Unauthorized,
+            Error::PayloadTooLarge(_) => status::PayloadTooLarge,
+            Error::InvalidPayload(_) | Error::InvalidRequest(_) => status::BadRequest,
+            Error::FileTooLarge(_) => status::UnprocessableEntity,
+            _ => status::InternalServerError,
+        }
+    }
 }
 
 impl fmt::Display for Error {
@@ -18,13 +41,23 @@ impl fmt::Display for Error {
         match self {
             Error::Runtime => write!(f, "Error creating runtime"),
             Error::NotFound => write!(f, "Playground not found"),
+            Error::RevisionNotFound => write!(f, "Revision not found"),
+            Error::Unauthorized => write!(f, "Missing or invalid edit token"),
             Error::Fetching => write!(f, "Error fetching playground"),
             Error::Creating => write!(f, "Error creating playground"),
+            Error::Updating => write!(f, "Error updating playground"),
+            Error::Deleting => write!(f, "Error deleting playground"),
             Error::ReadingRequest => write!(f, "Error reading request body"),
+            Error::PayloadTooLarge(limit) => {
+                write!(f, "Request body larger than {} bytes", limit)
+            }
+            Error::InvalidPayload(reason) => write!(f, "Invalid playground: {}", reason),
+            Error::InvalidRequest(reason) => write!(f, "Invalid request: {}", reason),
             Error::Deserializing => write!(f, "Error deserializing playground"),
             Error::Serializing => write!(f, "Error serializing playground"),
             Error::MissingFile(name) => write!(f, "File missing from playground: {}", name),
             Error::EmptyFile(name) => write!(f, "Empty file in playground: {}", name),
+            Error::FileTooLarge(name) => write!(f, "File too large to load: {}", name),
         }
     }
 }
@@ -0,0 +1,342 @@
+use fluent_bundle::{FluentArgs, FluentBundle, FluentResource, FluentValue};
+use fluent_langneg::{negotiate_languages, NegotiationStrategy};
+use fluent_syntax::ast;
+use fluent_syntax::unicode::unescape_unicode_to_string;
+use intl_pluralrules::{PluralCategory, PluralRuleType, PluralRules};
+use serde::Serialize;
+use serde_json::json;
+use std::collections::HashSet;
+use std::ops::Range;
+use unic_langid::LanguageIdentifier;
+
+use crate::ast::{walk_expressions, Spans};
+use crate::format::FUNCTIONS;
+
+/// A variant key compared with the selector.
+#[derive(Debug, Serialize)]
+pub struct Comparison {
+    pub key: String,
+    pub default: bool,
+    pub matched: bool,
+}
+
+/// How a select expression picked its variant while a message was formatted.
+#[derive(Debug, Serialize)]
+pub struct Selection {
+    pub message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub attribute: Option<String>,
+    /// The selector as written in the FTL, e.g. `$photoCount`.
+    pub expression: String,
+    /// Where the selector is, as in the AST.
+    pub span: serde_json::Value,
+    /// The bytes of the source the selector spans.
+    #[serde(skip)]
+    pub range: Range<usize>,
+    /// What the selector resolved to, or null if it couldn't be resolved.
+    pub value: serde_json::Value,
+    /// The plural category of the selector in the bundle's locale, when it
+    /// is a number.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub category: Option<&'static str>,
+    /// The variants in the order they were compared. Comparing stops at the
+    /// first match.
+    pub variants: Vec<Comparison>,
+    /// The key of the variant which was used, if any.
+    pub selected: Option<String>,
+    /// The index of the variant which was used.
+    #[serde(skip)]
+    pub variant: Option<usize>,
+    /// Whether no variant matched, so the default variant was used.
+    pub fallback: bool,
+}
+
+pub fn category_name(category: PluralCategory) -> &'static str {
+    match category {
+        PluralCategory::ZERO => "zero",
+        PluralCategory::ONE => "one",
+        PluralCategory::TWO => "two",
+        PluralCategory::FEW => "few",
+        PluralCategory::MANY => "many",
+        PluralCategory::OTHER => "other",
+    }
+}
+
+/// The plural rules `fluent-bundle` uses for `locale`: those of the closest
+/// locale with rules, or English.
+pub fn plural_rules(locale: LanguageIdentifier) -> Option<PluralRules> {
+    let default_locale: LanguageIdentifier = "en".parse().unwrap();
+    let supported = PluralRules::get_locales(PluralRuleType::CARDINAL);
+    let locale = negotiate_languages(
+        &[locale],
+        &supported,
+        Some(&default_locale),
+        NegotiationStrategy::Lookup,
+    )[0]
+    .clone();
+    PluralRules::create(locale, PluralRuleType::CARDINAL).ok()
+}
+
+pub fn key_name(key: &ast::VariantKey<&str>) -> String {
+    match key {
+        ast::VariantKey::Identifier { name } => name.to_string(),
+        ast::VariantKey::NumberLiteral { value } => value.to_string(),
+    }
+}
+
+/// Follows the resolution of messages, the way `fluent-bundle` selects
+/// variants.
+struct Tracer<'a, 's> {
+    bundle: &'a FluentBundle<FluentResource>,
+    args: &'a FluentArgs<'a>,
+    plural_rules: Option<PluralRules>,
+    source: &'s str,
+    spans: Spans<'s>,
+    message: String,
+    attribute: Option<String>,
+    selections: Vec<Selection>,
+}
+
+impl<'a, 's> Tracer<'a, 's> {
+    /// Resolve a selector like `fluent-bundle` does: literals, variables and
+    /// function calls keep their type, and references to messages and terms
+    /// are formatted to a string.
+    fn resolve<'v>(&'v self, selector: &ast::InlineExpression<&'v str>) -> FluentValue<'v> {
+        match selector {
+            ast::InlineExpression::StringLiteral { value } => {
+                unescape_unicode_to_string(value).into_owned().into()
+            }
+            ast::InlineExpression::NumberLiteral { value } => FluentValue::try_number(value),
+            ast::InlineExpression::VariableReference { id } => self
+                .args
+                .get(id.name)
+                .cloned()
+                .unwrap_or(FluentValue::Error),
+            ast::InlineExpression::FunctionReference { id, arguments } => {
+                let function = FUNCTIONS
+                    .iter()
+                    .find(|(name, _)| *name == id.name)
+                    .map(|&(_, function)| function);
+                let function = match function {
+                    Some(function) => function,
+                    None => return FluentValue::Error,
+                };
+                let positional: Vec<FluentValue> = arguments
+                    .positional
+                    .iter()
+                    .map(|argument| self.resolve(argument))
+                    .collect();
+                let mut named = FluentArgs::new();
+                for argument in &arguments.named {
+                    named.set(argument.name.name, self.resolve(&argument.value));
+                }
+                function(&positional, &named)
+            }
+            _ => {
+                let pattern = ast::Pattern {
+                    elements: vec![ast::PatternElement::Placeable {
+                        expression: ast::Expression::Inline(selector.clone()),
+                    }],
+                };
+                let mut errors = Vec::new();
+                self.bundle
+                    .format_pattern(&pattern, Some(self.args), &mut errors)
+                    .into_owned()
+                    .into()
+            }
+        }
+    }
+
+    /// Whether `key` selects `selector`, as in `FluentValue::matches`.
+    fn matches(&self, key: &ast::VariantKey<&str>, selector: &FluentValue) -> bool {
+        let key = match key {
+            ast::VariantKey::Identifier { name } => FluentValue::from(*name),
+            ast::VariantKey::NumberLiteral { value } => FluentValue::try_number(value),
+        };
+        match (&key, selector) {
+            (FluentValue::String(a), FluentValue::String(b)) => a == b,
+            (FluentValue::Number(a), FluentValue::Number(b)) => a == b,
+            (FluentValue::String(a), FluentValue::Number(_)) => {
+                Some(a.as_ref()) == self.category(selector)
+            }
+            _ => false,
+        }
+    }
+
+    fn category(&self, selector: &FluentValue) -> Option<&'static str> {
+        match (selector, &self.plural_rules) {
+            (FluentValue::Number(number), Some(rules)) => {
+                rules.select(number).ok().map(category_name)
+            }
+            _ => None,
+        }
+    }
+
+    /// Trace a select expression, and return the variant it selected, whose
+    /// pattern is followed next.
+    fn select<'p>(
+        &mut self,
+        selector: &ast::InlineExpression<&str>,
+        variants: &'p [ast::Variant<&'p str>],
+    ) -> &'p [ast::Variant<&'p str>] {
+        let value = self.resolve(selector);
+        let mut comparisons = Vec::new();
+        let mut selected = None;
+        if let FluentValue::String(_) | FluentValue::Number(_) = value {
+            for (index, variant) in variants.iter().enumerate() {
+                let matched = self.matches(&variant.key, &value);
+                comparisons.push(Comparison {
+                    key: key_name(&variant.key),
+                    default: variant.default,
+                    matched,
+                });
+                if matched {
+                    selected = Some((index, variant));
+                    break;
+                }
+            }
+        }
+        let fallback = selected.is_none();
+        if fallback {
+            selected = variants
+                .iter()
+                .enumerate()
+                .find(|(_, variant)| variant.default);
+        }
+
+        let (_, range) = self.spans.inline_expression(selector);
+        self.selections.push(Selection {
+            message: self.message.clone(),
+            attribute: self.attribute.clone(),
+            expression: self.source[range.clone()].to_string(),
+            span: self.spans.span(&range),
+            range,
+            value: match value {
+                FluentValue::String(ref text) => json!(text),
+                FluentValue::Number(ref number) => json!(number.value),
+                _ => serde_json::Value::Null,
+            },
+            category: self.category(&value),
+            variants: comparisons,
+            selected: selected.map(|(_, variant)| key_name(&variant.key)),
+            variant: selected.map(|(index, _)| index),
+            fallback,
+        });
+        match selected {
+            Some((index, _)) => &variants[index..=index],
+            None => &[],
+        }
+    }
+}
+
+/// The patterns of the messages of `resource`, with the id of their message
+/// and attribute. Like the bundle, only the first message with an id is used.
+pub fn message_patterns<'p, 's>(
+    resource: &'p ast::Resource<&'s str>,
+) -> Vec<(&'s str, Option<&'s str>, &'p ast::Pattern<&'s str>)> {
+    let mut seen = HashSet::new();
+    let mut patterns = Vec::new();
+    for entry in &resource.body {
+        let message = match entry {
+            ast::Entry::Message(message) if seen.insert(message.id.name) => message,
+            _ => continue,
+        };
+        if let Some(ref value) = message.value {
+            patterns.push((message.id.name, None, value));
+        }
+        for attribute in &message.attributes {
+            patterns.push((message.id.name, Some(attribute.id.name), &attribute.value));
+        }
+    }
+    patterns
+}
+
+/// Trace the select expressions which formatting each message of `resource`
+/// goes through, in the order they are resolved. Referenced messages and
+/// terms aren't followed; their own selections are traced where they're
+/// defined.
+pub fn explain(
+    source: &str,
+    resource: &ast::Resource<&str>,
+    bundle: &FluentBundle<FluentResource>,
+    args: &FluentArgs,
+    locale: LanguageIdentifier,
+) -> Vec<Selection> {
+    let mut tracer = Tracer {
+        bundle,
+        args,
+        plural_rules: plural_rules(locale),
+        source,
+        spans: Spans::new(source),
+        message: String::new(),
+        attribute: None,
+        selections: Vec::new(),
+    };
+    for (message, attribute, pattern) in message_patterns(resource) {
+        tracer.message = message.to_string();
+        tracer.attribute = attribute.map(str::to_string);
+        walk_expressions(pattern, &mut |expression| match expression {
+            ast::Expression::Select { selector, variants } => tracer.select(selector, variants),
+            ast::Expression::Inline(_) => &[],
+        });
+    }
+    tracer.selections
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::parse_resource;
+    use crate::format::create_bundle;
+
+    const PHOTOS: &str = "photos = { NUMBER($n) ->\n    [one] one\n    [few] few\n    [many] many\n   *[other] other\n}\n";
+
+    fn trace(source: &str, locale: &str, args: &FluentArgs) -> (Vec<Selection>, String) {
+        let locale: LanguageIdentifier = locale.parse().unwrap();
+        let bundle = create_bundle(locale.clone(), std::iter::once(source));
+        let (resource, _) = parse_resource(source);
+        let selections = explain(source, &resource, &bundle, args, locale);
+        let message = bundle.get_message("photos").unwrap();
+        let mut errors = Vec::new();
+        let formatted = bundle
+            .format_pattern(message.value().unwrap(), Some(args), &mut errors)
+            .into_owned();
+        (selections, formatted)
+    }
+
+    #[test]
+    fn traces_number_selectors_like_the_bundle() {
+        let numbers = (0..30).map(f64::from).chain(vec![1.5, 22.0, 101.0]);
+        for n in numbers {
+            let mut args = FluentArgs::new();
+            args.set("n", n);
+            let (selections, formatted) = trace(PHOTOS, "pl", &args);
+            assert_eq!(selections.len(), 1);
+            let selection = &selections[0];
+            assert_eq!(
+                selection.selected.as_deref(),
+                Some(formatted.as_str()),
+                "n = {}",
+                n
+            );
+            assert_eq!(selection.category, Some(formatted.as_str()), "n = {}", n);
+            assert!(!selection.fallback || formatted == "other");
+        }
+    }
+
+    #[test]
+    fn reports_the_plural_category_and_fallback() {
+        let source = "photos = { $n ->\n    [one] one\n   *[other] other\n}\n";
+        let mut args = FluentArgs::new();
+        args.set("n", 2);
+        let (selections, formatted) = trace(source, "pl", &args);
+        assert_eq!(formatted, "other");
+        let selection = &selections[0];
+        assert_eq!(selection.expression, "$n");
+        assert_eq!(selection.category, Some("few"));
+        assert_eq!(selection.selected.as_deref(), Some("other"));
+        assert!(selection.fallback);
+        assert_eq!(selection.variants.len(), 2);
+        assert!(selection.variants.iter().all(|variant| !variant.matched));
+    }
+}
@@ -0,0 +1,14 @@
+### Resource comments stand alone.
+
+## Group comments
+## can span lines.
+#
+# Empty lines count.
+
+# Attached to the message
+# below.
+hello = Hello, world!
+
+# Separated by a blank line.
+
+bye = Bye!
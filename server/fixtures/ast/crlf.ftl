@@ -0,0 +1,7 @@
+# Windows
+line-endings = One
+    two
+    .attr = { $x ->
+        [a] A  
+       *[b] B
+    }
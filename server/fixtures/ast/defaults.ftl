@@ -0,0 +1,12 @@
+# Try editing the translations below.
+# Set $variables' values in the Config tab.
+
+shared-photos =
+    {$userName} {$photoCount ->
+        [one] added a new photo
+       *[other] added {$photoCount} new photos
+    } to {$userGender ->
+        [male] his stream
+        [female] her stream
+       *[other] their stream
+    }.
@@ -0,0 +1,10 @@
+valid = Valid
+
+broken = { $var
+next = Next
+-term =
+missing =
+
+close = }
+unterminated = { "string
+last = Last
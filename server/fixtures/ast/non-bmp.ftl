@@ -0,0 +1,3 @@
+emoji = 😀 { $name } 🎉
+    𝒳 and more
+after = { "🦊" } { $count }
@@ -0,0 +1,12 @@
+-brand = { $case ->
+   *[nominative] Firefox
+    [locative] Firefoksie
+}
+    .gender = masculine
+
+about = O { -brand(case: "locative") }
+    .title = { -brand.gender ->
+        [masculine] Jego
+       *[other] Jej
+    }   
+    .label = { NUMBER($count, minimumFractionDigits: 2) } { "-" }
@@ -0,0 +1,409 @@
+use fluent_bundle::types::{
+    FluentNumber, FluentNumberCurrencyDisplayStyle, FluentNumberOptions, FluentNumberStyle,
+};
+use fluent_bundle::{FluentArgs, FluentBundle, FluentResource, FluentValue};
+use fluent_syntax::ast;
+use iron::{IronResult, Request, Response};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use unic_langid::LanguageIdentifier;
+
+use crate::ast::{parse_resource, walk_pattern};
+use crate::errors::Error;
+use crate::explain::{explain, Selection};
+use crate::json;
+use crate::playground::Resource;
+use crate::variables::{CurrencyDisplay, NumberOptions, NumberStyle, Variable, Variables};
+
+fn default_locale() -> String {
+    "en-US".to_string()
+}
+
+/// FTL sent to be formatted, with the variables to format it with.
+#[derive(Debug, Deserialize)]
+pub struct FormatRequest {
+    pub messages: String,
+    /// FTL added to the bundle after `messages`, in order.
+    #[serde(default)]
+    pub resources: Vec<Resource>,
+    #[serde(default)]
+    pub variables: Variables,
+    #[serde(default = "default_locale")]
+    pub locale: String,
+    /// Whether to trace how select expressions picked their variants.
+    #[serde(default)]
+    pub explain: bool,
+}
+
+impl FormatRequest {
+    pub fn langid(&self) -> Result<LanguageIdentifier, Error> {
+        self.locale.parse().map_err(|_| {
+            Error::InvalidRequest(format!(
+                "locale must be a BCP 47 language tag, not {:?}",
+                self.locale
+            ))
+        })
+    }
+
+    pub fn sources(&self) -> impl Iterator<Item = &str> {
+        std::iter::once(self.messages.as_str()).chain(
+            self.resources
+                .iter()
+                .map(|resource| resource.content.as_str()),
+        )
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct FormattedAttribute {
+    pub id: String,
+    pub value: String,
+}
+
+/// A message as the client's output panel shows it.
+#[derive(Debug, Serialize)]
+pub struct FormattedMessage {
+    pub id: String,
+    pub value: Option<String>,
+    pub attributes: Vec<FormattedAttribute>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Formatted {
+    pub messages: Vec<FormattedMessage>,
+    /// Errors from resolving the messages, in the order they happened,
+    /// followed by the formatting options which were ignored.
+    pub errors: Vec<String>,
+    /// The trace of select expressions, when explaining was asked for.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub selections: Option<Vec<Selection>>,
+}
+
+impl From<NumberStyle> for FluentNumberStyle {
+    fn from(style: NumberStyle) -> Self {
+        match style {
+            NumberStyle::Decimal => FluentNumberStyle::Decimal,
+            NumberStyle::Currency => FluentNumberStyle::Currency,
+            NumberStyle::Percent => FluentNumberStyle::Percent,
+        }
+    }
+}
+
+impl From<CurrencyDisplay> for FluentNumberCurrencyDisplayStyle {
+    fn from(display: CurrencyDisplay) -> Self {
+        match display {
+            CurrencyDisplay::Symbol => FluentNumberCurrencyDisplayStyle::Symbol,
+            CurrencyDisplay::Code => FluentNumberCurrencyDisplayStyle::Code,
+            CurrencyDisplay::Name => FluentNumberCurrencyDisplayStyle::Name,
+        }
+    }
+}
+
+impl From<&NumberOptions> for FluentNumberOptions {
+    fn from(options: &NumberOptions) -> Self {
+        let defaults = FluentNumberOptions::default();
+        FluentNumberOptions {
+            style: options.style.map_or(defaults.style, Into::into),
+            currency: options.currency.clone(),
+            currency_display: options
+                .currency_display
+                .map_or(defaults.currency_display, Into::into),
+            use_grouping: options.use_grouping.unwrap_or(defaults.use_grouping),
+            minimum_integer_digits: options.minimum_integer_digits.map(|n| n as usize),
+            minimum_fraction_digits: options.minimum_fraction_digits.map(|n| n as usize),
+            maximum_fraction_digits: options.maximum_fraction_digits.map(|n| n as usize),
+            minimum_significant_digits: options.minimum_significant_digits.map(|n| n as usize),
+            maximum_significant_digits: options.maximum_significant_digits.map(|n| n as usize),
+        }
+    }
+}
+
+/// The value Fluent is given for a variable.
+///
+/// `fluent-bundle` can't format dates, so they are passed as their RFC 3339
+/// text.
+pub fn fluent_value(variable: &Variable) -> FluentValue<'_> {
+    match variable {
+        Variable::String { value } => FluentValue::from(value.as_str()),
+        Variable::Number { value, options } => {
+            FluentValue::Number(FluentNumber::new(*value, options.into()))
+        }
+        Variable::Date { value, .. } => FluentValue::from(value.as_str()),
+    }
+}
+
+pub fn fluent_args(variables: &Variables) -> FluentArgs<'_> {
+    let mut args = FluentArgs::new();
+    for (name, variable) in &variables.0 {
+        args.set(name.as_str(), fluent_value(variable));
+    }
+    args
+}
+
+/// A function which can be called from FTL.
+pub type Function = for<'a> fn(&[FluentValue<'a>], &FluentArgs) -> FluentValue<'a>;
+
+/// The functions every bundle made by `create_bundle` has.
+pub const FUNCTIONS: &[(&str, Function)] = &[("NUMBER", number), ("DATETIME", datetime)];
+
+/// Fluent's built-in `NUMBER()`, which `fluent-bundle` leaves to the caller.
+fn number<'a>(positional: &[FluentValue<'a>], named: &FluentArgs) -> FluentValue<'a> {
+    let mut number = match positional.first() {
+        Some(FluentValue::Number(number)) => number.clone(),
+        Some(FluentValue::String(text)) => match text.parse::<FluentNumber>() {
+            Ok(number) => number,
+            Err(_) => return FluentValue::Error,
+        },
+        _ => return FluentValue::Error,
+    };
+    number.options.merge(named);
+    FluentValue::Number(number)
+}
+
+/// Fluent's built-in `DATETIME()`. Dates are only passed through, as text.
+fn datetime<'a>(positional: &[FluentValue<'a>], _named: &FluentArgs) -> FluentValue<'a> {
+    positional.first().cloned().unwrap_or(FluentValue::Error)
+}
+
+/// A bundle for `locale` with `sources` added in order, the way the client's
+/// `create_bundle` builds it: syntax errors are left to Junk, and messages
+/// already in the bundle aren't overridden.
+pub fn create_bundle<'a>(
+    locale: LanguageIdentifier,
+    sources: impl Iterator<Item = &'a str>,
+) -> FluentBundle<FluentResource> {
+    let mut bundle = FluentBundle::new(vec![locale]);
+    for &(name, function) in FUNCTIONS {
+        bundle
+            .add_function(name, function)
+            .expect("Functions are only added once");
+    }
+    for source in sources {
+        let resource = match FluentResource::try_new(source.to_string()) {
+            Ok(resource) => resource,
+            Err((resource, _)) => resource,
+        };
+        let _ = bundle.add_resource(resource);
+    }
+    bundle
+}
+
+/// Format every message of `resource`, like `format_messages` in the client.
+/// A message defined twice is only listed once, with the bundle's value.
+pub fn format_messages(
+    resource: &ast::Resource<&str>,
+    bundle: &FluentBundle<FluentResource>,
+    args: &FluentArgs,
+) -> Formatted {
+    let mut errors = Vec::new();
+    let mut messages = Vec::new();
+    let mut seen = HashSet::new();
+    for entry in &resource.body {
+        let id = match entry {
+            ast::Entry::Message(message) if seen.insert(message.id.name) => message.id.name,
+            _ => continue,
+        };
+        let message = match bundle.get_message(id) {
+            Some(message) => message,
+            None => continue,
+        };
+        let value = message.value().map(|value| {
+            bundle
+                .format_pattern(value, Some(args), &mut errors)
+                .into_owned()
+        });
+        let attributes = message
+            .attributes()
+            .map(|attribute| FormattedAttribute {
+                id: attribute.id().to_string(),
+                value: bundle
+                    .format_pattern(attribute.value(), Some(args), &mut errors)
+                    .into_owned(),
+            })
+            .collect();
+        messages.push(FormattedMessage {
+            id: id.to_string(),
+            value,
+            attributes,
+        });
+    }
+    Formatted {
+        messages,
+        errors: errors.iter().map(|error| error.to_string()).collect(),
+        selections: None,
+    }
+}
+
+/// Whether `fluent-bundle` honours a `NUMBER()` option. The others need the
+/// locale data the client gets from `Intl.NumberFormat`.
+fn is_supported_number_option(name: &str, value: &str) -> bool {
+    match name {
+        "minimumFractionDigits" | "useGrouping" => true,
+        "style" => value == "decimal",
+        _ => false,
+    }
+}
+
+/// Describe the options used by `resource` and the variables it references
+/// which are ignored when formatting, since the output would otherwise
+/// silently differ from the client's.
+pub fn unsupported_options(resource: &ast::Resource<&str>, variables: &Variables) -> Vec<String> {
+    let mut unsupported = Vec::new();
+    let mut report = |message: String| {
+        if !unsupported.contains(&message) {
+            unsupported.push(message);
+        }
+    };
+    let mut visit = |expression: &ast::InlineExpression<&str>| match expression {
+        ast::InlineExpression::FunctionReference { id, arguments } if id.name == "NUMBER" => {
+            for argument in &arguments.named {
+                let value = match argument.value {
+                    ast::InlineExpression::StringLiteral { value }
+                    | ast::InlineExpression::NumberLiteral { value } => value,
+                    _ => "",
+                };
+                if !is_supported_number_option(argument.name.name, value) {
+                    report(format!(
+                        "NUMBER() option {} is not supported and was ignored",
+                        argument.name.name
+                    ));
+                }
+            }
+        }
+        ast::InlineExpression::FunctionReference { id, .. } if id.name == "DATETIME" => {
+            report("DATETIME() is not supported; dates are shown as RFC 3339 text".to_string());
+        }
+        ast::InlineExpression::VariableReference { id } => match variables.0.get(id.name) {
+            Some(Variable::Number { options, .. }) => {
+                let options = serde_json::to_value(options).unwrap_or_default();
+                for (name, value) in options.as_object().into_iter().flatten() {
+                    if !is_supported_number_option(name, value.as_str().unwrap_or_default()) {
+                        report(format!(
+                            "${}: NUMBER() option {} is not supported and was ignored",
+                            id.name, name
+                        ));
+                    }
+                }
+            }
+            Some(Variable::Date { .. }) => report(format!(
+                "${}: dates are not supported and are shown as RFC 3339 text",
+                id.name
+            )),
+            _ => {}
+        },
+        _ => {}
+    };
+    for entry in &resource.body {
+        let (value, attributes) = match entry {
+            ast::Entry::Message(message) => (message.value.as_ref(), &message.attributes),
+            ast::Entry::Term(term) => (Some(&term.value), &term.attributes),
+            _ => continue,
+        };
+        for pattern in value
+            .into_iter()
+            .chain(attributes.iter().map(|attribute| &attribute.value))
+        {
+            walk_pattern(pattern, &mut visit);
+        }
+    }
+    unsupported
+}
+
+/// Format the messages of the request with `variables`, tracing how their
+/// variants are selected if the request asks for it.
+pub fn format_variables(
+    request: &FormatRequest,
+    resource: &ast::Resource<&str>,
+    bundle: &FluentBundle<FluentResource>,
+    variables: &Variables,
+) -> Result<Formatted, Error> {
+    let args = fluent_args(variables);
+    let mut formatted = format_messages(resource, bundle, &args);
+    formatted
+        .errors
+        .extend(unsupported_options(resource, variables));
+    if request.explain {
+        formatted.selections = Some(explain(
+            &request.messages,
+            resource,
+            bundle,
+            &args,
+            request.langid()?,
+        ));
+    }
+    Ok(formatted)
+}
+
+fn format_request(request: &FormatRequest) -> Result<Formatted, Error> {
+    let bundle = create_bundle(request.langid()?, request.sources());
+    let (resource, _) = parse_resource(&request.messages);
+    format_variables(request, &resource, &bundle, &request.variables)
+}
+
+/// Format the FTL in the request with its variables.
+pub fn format(req: &mut Request) -> IronResult<Response> {
+    let formatted = json::read(req).and_then(|request| format_request(&request));
+    match formatted {
+        Ok(formatted) => json::respond(formatted),
+        Err(err) => json::error(err.status(), err),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lists_duplicate_messages_once() {
+        let source = "hello = Hello\nhello = Hi\nbye = Bye\n";
+        let bundle = create_bundle("en-US".parse().unwrap(), std::iter::once(source));
+        let (resource, _) = parse_resource(source);
+        let formatted = format_messages(&resource, &bundle, &FluentArgs::new());
+        let ids: Vec<&str> = formatted.messages.iter().map(|m| m.id.as_str()).collect();
+        assert_eq!(ids, ["hello", "bye"]);
+        assert_eq!(formatted.messages[0].value.as_deref(), Some("Hello"));
+    }
+
+    #[test]
+    fn reports_options_formatted_differently_from_the_client() {
+        let source = "\
+share = { NUMBER($ratio, style: \"percent\") } { NUMBER($count, minimumFractionDigits: 1) }
+due = { DATETIME($date) } { $price } { $count }
+";
+        let variables: Variables = serde_json::from_value(serde_json::json!({
+            "ratio": 0.5,
+            "count": 3,
+            "date": "2020-03-01T12:00:00.000Z",
+            "price": {
+                "type": "number",
+                "value": 9.5,
+                "options": { "style": "currency", "currency": "EUR", "useGrouping": false },
+            },
+        }))
+        .unwrap();
+        let (resource, _) = parse_resource(source);
+        assert_eq!(
+            unsupported_options(&resource, &variables),
+            [
+                "NUMBER() option style is not supported and was ignored",
+                "DATETIME() is not supported; dates are shown as RFC 3339 text",
+                "$date: dates are not supported and are shown as RFC 3339 text",
+                "$price: NUMBER() option currency is not supported and was ignored",
+                "$price: NUMBER() option style is not supported and was ignored",
+            ]
+        );
+    }
+
+    #[test]
+    fn formats_supported_options_without_errors() {
+        let source = "price = { NUMBER($price, style: \"decimal\", minimumFractionDigits: 2) }\n";
+        let variables: Variables =
+            serde_json::from_value(serde_json::json!({ "price": 9.5 })).unwrap();
+        let bundle = create_bundle("en-US".parse().unwrap(), std::iter::once(source));
+        let (resource, _) = parse_resource(source);
+        let formatted = format_messages(&resource, &bundle, &fluent_args(&variables));
+        assert_eq!(formatted.messages[0].value.as_deref(), Some("9.50"));
+        assert!(formatted.errors.is_empty());
+        assert!(unsupported_options(&resource, &variables).is_empty());
+    }
+}
@@ -0,0 +1,327 @@
+use fluent_syntax::ast;
+use iron::{IronResult, Request, Response};
+use router::Router;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use crate::ast::{parse_resource, walk_expressions, Spans};
+use crate::json;
+use crate::middleware::StoreMiddleware;
+use crate::playground::{sources, Resource};
+use crate::variables::{DateTimeOptions, NumberOptions, Variable, Variables};
+
+/// The plural categories of CLDR, which select expressions on numbers use as
+/// variant keys.
+pub const PLURAL_CATEGORIES: &[&str] = &["zero", "one", "two", "few", "many", "other"];
+
+/// The value given to numbers which nothing else suggests a value for. It
+/// falls into the `other` category, or `few`, in most locales.
+const EXAMPLE_NUMBER: f64 = 3.0;
+const EXAMPLE_DATE: &str = "2019-03-28T14:30:00.000Z";
+
+/// FTL to infer the variables of.
+#[derive(Debug, Deserialize)]
+struct Source {
+    messages: String,
+    #[serde(default)]
+    resources: Vec<Resource>,
+}
+
+/// Where a variable is used.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Context {
+    /// Inside a placeable, on its own.
+    Placeable,
+    /// As the selector of a select expression.
+    Selector,
+    /// As an argument of `NUMBER()`.
+    Number,
+    /// As an argument of `DATETIME()`.
+    Datetime,
+    /// As an argument of another function.
+    FunctionArgument,
+    /// As an argument of a parameterized term.
+    TermArgument,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Usage {
+    /// The message the variable is used in.
+    pub message: String,
+    pub file: String,
+    pub context: Context,
+    pub span: serde_json::Value,
+}
+
+#[derive(Debug, Serialize)]
+pub struct InferredVariable {
+    pub usages: Vec<Usage>,
+    /// The variant keys of the select expressions on the variable.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub keys: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct Inferred {
+    variables: BTreeMap<String, InferredVariable>,
+    /// A starting point for the playground's variables.
+    skeleton: Variables,
+}
+
+impl InferredVariable {
+    fn is_used_as(&self, context: Context) -> bool {
+        self.usages.iter().any(|usage| usage.context == context)
+    }
+
+    /// Whether the variable is selected on with plural categories or number
+    /// literals, rather than strings.
+    fn has_numeric_keys(&self) -> bool {
+        !self.keys.is_empty()
+            && self
+                .keys
+                .iter()
+                .all(|key| PLURAL_CATEGORIES.contains(&key.as_str()) || key.parse::<f64>().is_ok())
+    }
+
+    /// Whether the variable is used as a number rather than a string.
+    pub fn is_number(&self) -> bool {
+        self.is_used_as(Context::Number) || self.has_numeric_keys()
+    }
+
+    /// An example value fitting how the variable is used.
+    pub fn example(&self, name: &str) -> Variable {
+        if self.is_used_as(Context::Datetime) {
+            Variable::Date {
+                value: EXAMPLE_DATE.to_string(),
+                options: DateTimeOptions::default(),
+            }
+        } else if self.is_number() {
+            Variable::Number {
+                value: EXAMPLE_NUMBER,
+                options: NumberOptions::default(),
+            }
+        } else {
+            let value = self
+                .keys
+                .first()
+                .cloned()
+                .unwrap_or_else(|| name.to_string());
+            Variable::String { value }
+        }
+    }
+}
+
+/// The variable a select expression selects on: either the selector itself,
+/// or the variable `NUMBER()` or `DATETIME()` is called on.
+fn selected_variable<'p, 's>(
+    selector: &'p ast::InlineExpression<&'s str>,
+) -> Option<&'p ast::Identifier<&'s str>> {
+    match selector {
+        ast::InlineExpression::VariableReference { id } => Some(id),
+        ast::InlineExpression::FunctionReference { id, arguments }
+            if id.name == "NUMBER" || id.name == "DATETIME" =>
+        {
+            match arguments.positional.first() {
+                Some(ast::InlineExpression::VariableReference { id }) => Some(id),
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+/// Collects the variables used in the messages of one FTL file.
+struct Collector<'a, 's> {
+    file: &'a str,
+    spans: Spans<'s>,
+    message: String,
+    variables: &'a mut BTreeMap<String, InferredVariable>,
+}
+
+impl<'a, 's> Collector<'a, 's> {
+    fn variable(&mut self, name: &str) -> &mut InferredVariable {
+        self.variables
+            .entry(name.to_string())
+            .or_insert_with(|| InferredVariable {
+                usages: Vec::new(),
+                keys: Vec::new(),
+            })
+    }
+
+    fn use_variable(&mut self, id: &ast::Identifier<&str>, context: Context) {
+        let range = self.spans.range(id.name);
+        let usage = Usage {
+            message: self.message.clone(),
+            file: self.file.to_string(),
+            context,
+            span: self.spans.span(&(range.start.saturating_sub(1)..range.end)),
+        };
+        self.variable(id.name).usages.push(usage);
+    }
+
+    /// Visit an expression, and return the variants whose patterns are
+    /// visited next: all of them.
+    fn expression<'p>(
+        &mut self,
+        expression: &'p ast::Expression<&'p str>,
+    ) -> &'p [ast::Variant<&'p str>] {
+        match expression {
+            ast::Expression::Select { selector, variants } => {
+                self.inline_expression(selector, Context::Selector);
+                if let Some(id) = selected_variable(selector) {
+                    let keys: Vec<String> = variants
+                        .iter()
+                        .filter(|variant| !variant.default)
+                        .chain(variants.iter().filter(|variant| variant.default))
+                        .map(|variant| match variant.key {
+                            ast::VariantKey::Identifier { name } => name.to_string(),
+                            ast::VariantKey::NumberLiteral { value } => value.to_string(),
+                        })
+                        .collect();
+                    let variable = self.variable(id.name);
+                    for key in keys {
+                        if !variable.keys.contains(&key) {
+                            variable.keys.push(key);
+                        }
+                    }
+                }
+                variants
+            }
+            ast::Expression::Inline(expression) => {
+                self.inline_expression(expression, Context::Placeable);
+                &[]
+            }
+        }
+    }
+
+    /// Visit an inline expression, where a variable on its own would be used
+    /// as `context`. Nested placeables are visited by `walk_expressions`.
+    fn inline_expression(&mut self, expression: &ast::InlineExpression<&str>, context: Context) {
+        match expression {
+            ast::InlineExpression::VariableReference { id } => self.use_variable(id, context),
+            ast::InlineExpression::FunctionReference { id, arguments } => {
+                let context = match id.name {
+                    "NUMBER" => Context::Number,
+                    "DATETIME" => Context::Datetime,
+                    _ => Context::FunctionArgument,
+                };
+                self.arguments(arguments, context);
+            }
+            ast::InlineExpression::TermReference {
+                arguments: Some(arguments),
+                ..
+            } => self.arguments(arguments, Context::TermArgument),
+            _ => {}
+        }
+    }
+
+    fn arguments(&mut self, arguments: &ast::CallArguments<&str>, context: Context) {
+        for argument in &arguments.positional {
+            self.inline_expression(argument, context);
+        }
+        for argument in &arguments.named {
+            self.inline_expression(&argument.value, context);
+        }
+    }
+}
+
+/// Find every variable used by the messages in `files`, and how. Terms are
+/// skipped, since their variables are their own arguments.
+pub fn infer<'a>(
+    files: impl Iterator<Item = (&'a str, &'a str)>,
+) -> BTreeMap<String, InferredVariable> {
+    let mut variables = BTreeMap::new();
+    for (file, source) in files {
+        let (resource, _) = parse_resource(source);
+        let mut collector = Collector {
+            file,
+            spans: Spans::new(source),
+            message: String::new(),
+            variables: &mut variables,
+        };
+        for entry in &resource.body {
+            if let ast::Entry::Message(message) = entry {
+                collector.message = message.id.name.to_string();
+                let patterns = message
+                    .value
+                    .iter()
+                    .chain(message.attributes.iter().map(|attribute| &attribute.value));
+                for pattern in patterns {
+                    walk_expressions(pattern, &mut |expression| collector.expression(expression));
+                }
+            }
+        }
+    }
+    variables
+}
+
+/// Variables with an example value for each of `variables`.
+pub fn skeleton(variables: &BTreeMap<String, InferredVariable>) -> Variables {
+    Variables(
+        variables
+            .iter()
+            .map(|(name, variable)| (name.clone(), variable.example(name)))
+            .collect(),
+    )
+}
+
+fn respond(variables: BTreeMap<String, InferredVariable>) -> IronResult<Response> {
+    json::respond(Inferred {
+        skeleton: skeleton(&variables),
+        variables,
+    })
+}
+
+pub fn get(req: &mut Request) -> IronResult<Response> {
+    let store = &req.extensions.get::<StoreMiddleware>().unwrap().store;
+    let params = req.extensions.get::<Router>().unwrap();
+    let id = params.find("id").expect("No route parameter called id");
+    match store.get(id) {
+        Ok(playground) => respond(infer(playground.sources())),
+        Err(err) => json::error(err.status(), err),
+    }
+}
+
+pub fn post(req: &mut Request) -> IronResult<Response> {
+    match json::read::<Source>(req) {
+        Ok(source) => respond(infer(sources(&source.messages, &source.resources))),
+        Err(err) => json::error(err.status(), err),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn infer_source(source: &str) -> BTreeMap<String, InferredVariable> {
+        infer(std::iter::once(("playground.ftl", source)))
+    }
+
+    #[test]
+    fn records_keys_of_number_selectors() {
+        let variables = infer_source(
+            "photos = { NUMBER($photoCount) ->\n    [one] One photo\n   *[other] Photos\n}\n",
+        );
+        let photo_count = &variables["photoCount"];
+        assert_eq!(photo_count.keys, ["one", "other"]);
+        assert!(photo_count.is_used_as(Context::Number));
+        assert!(matches!(
+            photo_count.example("photoCount"),
+            Variable::Number { .. }
+        ));
+    }
+
+    #[test]
+    fn uses_a_key_as_the_example_of_string_selectors() {
+        let variables = infer_source(
+            "shared = { $userGender ->\n    [male] his\n    [female] her\n   *[other] their\n} { $userName }\n",
+        );
+        assert_eq!(variables["userGender"].keys, ["male", "female", "other"]);
+        match variables["userGender"].example("userGender") {
+            Variable::String { value } => assert_eq!(value, "male"),
+            other => panic!("Expected a string, got {:?}", other),
+        }
+        assert!(variables["userName"].is_used_as(Context::Placeable));
+    }
+}
@@ -1,7 +1,15 @@
-use iron::{headers::ContentType, modifiers::Header, status, IronResult, Response};
-use serde::Serialize;
-use serde_json;
+use iron::{
+    headers::{ContentLength, ContentType},
+    modifiers::Header,
+    status, IronResult, Request, Response,
+};
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::json;
 use std::fmt::Display;
+use std::io::Read;
+
+use crate::errors::Error;
+use crate::middleware::ConfigMiddleware;
 
 pub fn respond(response: impl Serialize) -> IronResult<Response> {
     match serde_json::ser::to_string(&response) {
@@ -22,6 +30,37 @@ pub fn error(status: status::Status, message: impl Display) -> IronResult<Respon
     Ok(Response::with((
         status,
         Header(ContentType::json()),
-        format!(r#"{{"error": "{}"}}"#, message),
+        json!({ "error": message.to_string() }).to_string(),
     )))
 }
+
+/// Read a JSON request body, refusing bodies larger than the configured limit.
+pub fn read<T: DeserializeOwned>(req: &mut Request) -> Result<T, Error> {
+    let limit = req
+        .extensions
+        .get::<ConfigMiddleware>()
+        .unwrap()
+        .config
+        .max_payload_size;
+    if let Some(&ContentLength(length)) = req.headers.get::<ContentLength>() {
+        if length > limit {
+            return Err(Error::PayloadTooLarge(limit));
+        }
+    }
+    let mut payload = Vec::new();
+    if req
+        .body
+        .by_ref()
+        .take(limit + 1)
+        .read_to_end(&mut payload)
+        .is_err()
+    {
+        return Err(Error::ReadingRequest);
+    }
+    if payload.len() as u64 > limit {
+        return Err(Error::PayloadTooLarge(limit));
+    }
+    let payload = String::from_utf8(payload)
+        .map_err(|_| Error::InvalidRequest("body must be UTF-8".to_string()))?;
+    serde_json::from_str(&payload).map_err(|err| Error::InvalidRequest(err.to_string()))
+}
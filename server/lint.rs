@@ -0,0 +1,389 @@
+use fluent_syntax::ast;
+use fluent_syntax::parser::{ErrorKind, ParserError};
+use iron::{IronResult, Request, Response};
+use router::Router;
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::ops::Range;
+
+use crate::ast::{parse_resource, walk_pattern, Spans};
+use crate::json;
+use crate::middleware::StoreMiddleware;
+use crate::playground::{localized_filename, read_payload, Playground};
+use crate::variables::Variables;
+
+/// Something in a playground's FTL which is likely a mistake.
+#[derive(Debug, Serialize)]
+pub struct Problem {
+    pub code: &'static str,
+    pub message: String,
+    /// The name under which the FTL file is stored.
+    pub file: String,
+    pub span: serde_json::Value,
+}
+
+/// A parsed FTL file of a playground.
+struct File<'s> {
+    name: String,
+    spans: Spans<'s>,
+    resource: ast::Resource<&'s str>,
+    errors: Vec<ParserError>,
+}
+
+impl<'s> File<'s> {
+    fn parse(name: String, source: &'s str) -> Self {
+        let (resource, errors) = parse_resource(source);
+        File {
+            name,
+            spans: Spans::new(source),
+            resource,
+            errors,
+        }
+    }
+
+    fn problem(&self, code: &'static str, message: String, range: Range<usize>) -> Problem {
+        Problem {
+            code,
+            message,
+            file: self.name.clone(),
+            span: self.spans.span(&range),
+        }
+    }
+
+    /// The range of an identifier, along with the sigil before it, if any.
+    fn id_range(&self, id: &ast::Identifier<&str>, sigil: usize) -> Range<usize> {
+        let range = self.spans.range(id.name);
+        range.start.saturating_sub(sigil)..range.end
+    }
+}
+
+/// The patterns of an entry, and whether it is a term, whose variables are
+/// its call arguments rather than the playground's variables.
+fn patterns<'p, 's>(entry: &'p ast::Entry<&'s str>) -> (Vec<&'p ast::Pattern<&'s str>>, bool) {
+    let (value, attributes, is_term) = match entry {
+        ast::Entry::Message(message) => (message.value.as_ref(), &message.attributes, false),
+        ast::Entry::Term(term) => (Some(&term.value), &term.attributes, true),
+        _ => return (Vec::new(), false),
+    };
+    let patterns = value
+        .into_iter()
+        .chain(attributes.iter().map(|attribute| &attribute.value))
+        .collect();
+    (patterns, is_term)
+}
+
+fn attribute_names<'s>(attributes: &[ast::Attribute<&'s str>]) -> HashSet<&'s str> {
+    attributes
+        .iter()
+        .map(|attribute| attribute.id.name)
+        .collect()
+}
+
+/// Check the FTL files which make up one locale's bundle together.
+fn lint_files(files: &[File], variables: &Variables) -> Vec<Problem> {
+    let mut problems = Vec::new();
+    let mut messages = HashMap::new();
+    let mut terms = HashMap::new();
+    let mut term_definitions = Vec::new();
+
+    for file in files {
+        for error in &file.errors {
+            if error.kind == ErrorKind::MissingDefaultVariant {
+                problems.push(file.problem(
+                    "missing-default-variant",
+                    "Select expression has no default variant".to_string(),
+                    error.pos.clone(),
+                ));
+            }
+        }
+        for entry in &file.resource.body {
+            let (id, attributes, definitions, sigil) = match entry {
+                ast::Entry::Message(message) => {
+                    (&message.id, &message.attributes, &mut messages, "")
+                }
+                ast::Entry::Term(term) => {
+                    term_definitions.push((file, &term.id));
+                    (&term.id, &term.attributes, &mut terms, "-")
+                }
+                _ => continue,
+            };
+            if definitions.contains_key(id.name) {
+                problems.push(file.problem(
+                    "duplicate-id",
+                    format!("{}{} is already defined", sigil, id.name),
+                    file.id_range(id, sigil.len()),
+                ));
+            } else {
+                definitions.insert(id.name, attribute_names(attributes));
+            }
+        }
+    }
+
+    let mut used_terms = HashSet::new();
+    for file in files {
+        for entry in &file.resource.body {
+            let (patterns, is_term) = patterns(entry);
+            for pattern in patterns {
+                walk_pattern(pattern, &mut |expression| match expression {
+                    ast::InlineExpression::MessageReference { id, attribute } => {
+                        if let Some(message) =
+                            check_reference(file, &messages, id, attribute.as_ref(), "")
+                        {
+                            problems.push(message);
+                        }
+                    }
+                    ast::InlineExpression::TermReference { id, attribute, .. } => {
+                        used_terms.insert(id.name);
+                        if let Some(message) =
+                            check_reference(file, &terms, id, attribute.as_ref(), "-")
+                        {
+                            problems.push(message);
+                        }
+                    }
+                    ast::InlineExpression::VariableReference { id }
+                        if !is_term && !variables.0.contains_key(id.name) =>
+                    {
+                        problems.push(file.problem(
+                            "unknown-variable",
+                            format!("${} is not defined in the variables", id.name),
+                            file.id_range(id, 1),
+                        ));
+                    }
+                    _ => {}
+                });
+            }
+        }
+    }
+
+    for (file, id) in term_definitions {
+        if !used_terms.contains(id.name) {
+            problems.push(file.problem(
+                "unused-term",
+                format!("-{} is never used", id.name),
+                file.id_range(id, 1),
+            ));
+        }
+    }
+    problems
+}
+
+/// Report a reference to a message or term, or to an attribute of one,
+/// which isn't defined.
+fn check_reference(
+    file: &File,
+    definitions: &HashMap<&str, HashSet<&str>>,
+    id: &ast::Identifier<&str>,
+    attribute: Option<&ast::Identifier<&str>>,
+    sigil: &str,
+) -> Option<Problem> {
+    let kind = if sigil.is_empty() { "message" } else { "term" };
+    let range = file.id_range(id, sigil.len());
+    match (definitions.get(id.name), attribute) {
+        (None, _) => Some(file.problem(
+            "unknown-reference",
+            format!("Unknown {}: {}{}", kind, sigil, id.name),
+            range,
+        )),
+        (Some(attributes), Some(attribute)) if !attributes.contains(attribute.name) => {
+            Some(file.problem(
+                "unknown-reference",
+                format!("Unknown attribute: {}{}.{}", sigil, id.name, attribute.name),
+                range.start..file.spans.range(attribute.name).end,
+            ))
+        }
+        _ => None,
+    }
+}
+
+/// Check every locale of a playground.
+pub fn lint(playground: &Playground) -> Vec<Problem> {
+    let files: Vec<File> = playground
+        .sources()
+        .map(|(name, content)| File::parse(name.to_string(), content))
+        .collect();
+    let mut problems = lint_files(&files, &playground.variables);
+    for translation in &playground.translations {
+        let files: Vec<File> = translation
+            .sources()
+            .map(|(name, content)| {
+                File::parse(localized_filename(name, &translation.locale), content)
+            })
+            .collect();
+        problems.extend(lint_files(&files, &playground.variables));
+    }
+    problems
+}
+
+pub fn get(req: &mut Request) -> IronResult<Response> {
+    let store = &req.extensions.get::<StoreMiddleware>().unwrap().store;
+    let params = req.extensions.get::<Router>().unwrap();
+    let id = params.find("id").expect("No route parameter called id");
+    match store.get(id) {
+        Ok(playground) => json::respond(lint(&playground)),
+        Err(err) => json::error(err.status(), err),
+    }
+}
+
+pub fn post(req: &mut Request) -> IronResult<Response> {
+    match read_payload(req) {
+        Ok(playground) => json::respond(lint(&playground)),
+        Err(err) => json::error(err.status(), err),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The problems in `sources`, with the offsets of their spans.
+    fn check(sources: &[(&str, &str)], variables: serde_json::Value) -> Vec<(String, u64, u64)> {
+        let files: Vec<File> = sources
+            .iter()
+            .map(|(name, source)| File::parse(name.to_string(), source))
+            .collect();
+        let variables: Variables = serde_json::from_value(variables).unwrap();
+        lint_files(&files, &variables)
+            .into_iter()
+            .map(|problem| {
+                (
+                    format!("{} {}: {}", problem.file, problem.code, problem.message),
+                    problem.span["start"].as_u64().unwrap(),
+                    problem.span["end"].as_u64().unwrap(),
+                )
+            })
+            .collect()
+    }
+
+    fn problem(description: &str, start: u64, end: u64) -> (String, u64, u64) {
+        (description.to_string(), start, end)
+    }
+
+    #[test]
+    fn accepts_correct_ftl() {
+        let source = "-brand = Firefox\n    .gender = masculine\nhello = { -brand.gender ->\n   *[masculine] { $name } { -brand }\n}\n    .title = { hello }\n";
+        assert_eq!(
+            check(
+                &[("playground.ftl", source)],
+                serde_json::json!({ "name": "Anne" })
+            ),
+            []
+        );
+    }
+
+    #[test]
+    fn reports_unknown_messages_terms_and_attributes() {
+        let source = "hello = { bye } { -brand } { hello.title }\n";
+        assert_eq!(
+            check(&[("playground.ftl", source)], serde_json::json!({})),
+            [
+                problem(
+                    "playground.ftl unknown-reference: Unknown message: bye",
+                    10,
+                    13
+                ),
+                problem(
+                    "playground.ftl unknown-reference: Unknown term: -brand",
+                    18,
+                    24
+                ),
+                problem(
+                    "playground.ftl unknown-reference: Unknown attribute: hello.title",
+                    29,
+                    40
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn resolves_references_across_files() {
+        let sources = [
+            ("playground.ftl", "hello = { -brand }\n"),
+            ("brand.ftl", "-brand = Firefox\n"),
+        ];
+        assert_eq!(check(&sources, serde_json::json!({})), []);
+    }
+
+    #[test]
+    fn reports_unused_terms() {
+        let sources = [
+            ("playground.ftl", "hello = Hello\n"),
+            ("brand.ftl", "-brand = Firefox\n-vendor = Mozilla\n"),
+        ];
+        assert_eq!(
+            check(&sources, serde_json::json!({})),
+            [
+                problem("brand.ftl unused-term: -brand is never used", 0, 6),
+                problem("brand.ftl unused-term: -vendor is never used", 17, 24),
+            ]
+        );
+    }
+
+    #[test]
+    fn reports_missing_default_variants() {
+        let source = "emails = { $n ->\n    [one] One email\n    [other] Emails\n}\n";
+        assert_eq!(
+            check(&[("playground.ftl", source)], serde_json::json!({ "n": 1 })),
+            [problem(
+                "playground.ftl missing-default-variant: Select expression has no default variant",
+                56,
+                57
+            )]
+        );
+    }
+
+    #[test]
+    fn reports_duplicate_ids() {
+        let sources = [
+            (
+                "playground.ftl",
+                "hello = Hello\n-brand = Firefox\nhello = Hi\n",
+            ),
+            ("brand.ftl", "-brand = Fenix\nbye = { -brand }\n"),
+        ];
+        assert_eq!(
+            check(&sources, serde_json::json!({})),
+            [
+                problem(
+                    "playground.ftl duplicate-id: hello is already defined",
+                    31,
+                    36
+                ),
+                problem("brand.ftl duplicate-id: -brand is already defined", 0, 6),
+            ]
+        );
+    }
+
+    #[test]
+    fn reports_unknown_variables_outside_terms() {
+        let source =
+            "-brand = { $case ->\n   *[nominative] Firefox\n}\nhello = { $name } { $user }\n";
+        assert_eq!(
+            check(
+                &[("playground.ftl", source)],
+                serde_json::json!({ "name": "Anne" })
+            ),
+            [
+                problem(
+                    "playground.ftl unknown-variable: $user is not defined in the variables",
+                    67,
+                    72
+                ),
+                problem("playground.ftl unused-term: -brand is never used", 0, 6),
+            ]
+        );
+    }
+
+    #[test]
+    fn counts_spans_in_utf16() {
+        let source = "emoji = 😀 { $missing }\n";
+        assert_eq!(
+            check(&[("playground.ftl", source)], serde_json::json!({})),
+            [problem(
+                "playground.ftl unknown-variable: $missing is not defined in the variables",
+                13,
+                21
+            )]
+        );
+    }
+}
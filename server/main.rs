@@ -1,44 +1,106 @@
 use corsware::{AllowedOrigins, CorsMiddleware, Origin, UniCase};
-use hubcaps::{Credentials, Github};
 use iron::{method::Method, Chain, Iron};
 use router::Router;
 use std::collections::HashSet;
 use std::env;
 
+mod ast;
+mod config;
+mod coverage;
+mod diagnostics;
 mod errors;
+mod explain;
+mod format;
+mod infer;
 mod info;
 mod json;
+mod lint;
+mod matrix;
 mod middleware;
+mod migrations;
 mod playground;
-use crate::middleware::GistsMiddleware;
+mod serialize;
+mod sets;
+mod setup;
+mod store;
+mod token;
+mod variables;
+use crate::config::Config;
+use crate::middleware::{ConfigMiddleware, StoreMiddleware};
+use crate::store::{FsStore, GistStore, MemoryStore, SqliteStore};
 
 fn main() {
     let port = env::var("PORT")
         .unwrap_or_else(|_| "8080".to_string())
         .parse()
         .expect("Unable to parse PORT into a number");
-    let token = env::var("GITHUB_API_TOKEN").expect("Missing GitHub API token");
-
-    let github = Github::new(
-        concat!(env!("CARGO_PKG_NAME"), "/", env!("CARGO_PKG_VERSION")),
-        Credentials::Token(token),
-    );
-    let gists = github.gists();
+    let config = Config::from_env();
+    let backend = env::var("PLAYGROUND_STORE").unwrap_or_else(|_| "gist".to_string());
+    let store = match backend.as_str() {
+        "gist" => {
+            let token = env::var("GITHUB_API_TOKEN").expect("Missing GitHub API token");
+            StoreMiddleware::new(GistStore::new(token, config.max_file_size))
+        }
+        "fs" => {
+            let dir = env::var("PLAYGROUND_DIR").unwrap_or_else(|_| "playgrounds".to_string());
+            StoreMiddleware::new(FsStore::new(dir).expect("Unable to create PLAYGROUND_DIR"))
+        }
+        "sqlite" => {
+            let path =
+                env::var("PLAYGROUND_DB").unwrap_or_else(|_| "playgrounds.sqlite".to_string());
+            StoreMiddleware::new(SqliteStore::open(path).expect("Unable to open PLAYGROUND_DB"))
+        }
+        "memory" => StoreMiddleware::new(MemoryStore::new()),
+        other => panic!("Unknown PLAYGROUND_STORE: {}", other),
+    };
 
     let mut router = Router::new();
     router.get("/", info::get, "info");
+    router.post("/parse", ast::parse, "parse");
+    router.post("/format", format::format, "format");
+    router.post("/format/matrix", matrix::format, "format_matrix");
+    router.post("/coverage", coverage::post, "coverage");
+    router.post("/diagnostics", diagnostics::check, "diagnostics");
+    router.post("/serialize", serialize::serialize, "serialize");
+    router.post("/lint", lint::post, "lint");
+    router.post("/variables", infer::post, "infer_variables");
+    router.post("/variables/sets", sets::post, "variable_sets");
     router.get("/playgrounds/:id", playground::get, "get_playground");
+    router.post("/playgrounds", playground::create, "create_playground");
+    router.put("/playgrounds/:id", playground::update, "update_playground");
+    router.delete("/playgrounds/:id", playground::delete, "delete_playground");
+    router.post("/playgrounds/:id/fork", playground::fork, "fork_playground");
+    router.get(
+        "/playgrounds/:id/revisions",
+        playground::revisions,
+        "list_revisions",
+    );
+    router.get(
+        "/playgrounds/:id/revisions/:rev",
+        playground::revision,
+        "get_revision",
+    );
+    router.get("/playgrounds/:id/lint", lint::get, "lint_playground");
+    router.get(
+        "/playgrounds/:id/variables",
+        infer::get,
+        "infer_playground_variables",
+    );
 
     let mut origins = HashSet::new();
     origins.insert(Origin::parse("https://projectfluent.org").unwrap());
     origins.insert(Origin::parse("https://www.projectfluent.org").unwrap());
 
     let mut chain = Chain::new(router);
-    chain.link_before(GistsMiddleware::new(gists));
+    chain.link_before(ConfigMiddleware::new(config));
+    chain.link_before(store);
     chain.link_around(CorsMiddleware {
         allowed_origins: AllowedOrigins::Specific(origins),
-        allowed_headers: vec![UniCase("Content-Type".to_owned())],
-        allowed_methods: vec![Method::Get, Method::Post],
+        allowed_headers: vec![
+            UniCase("Content-Type".to_owned()),
+            UniCase("Authorization".to_owned()),
+        ],
+        allowed_methods: vec![Method::Get, Method::Post, Method::Put, Method::Delete],
         exposed_headers: vec![],
         allow_credentials: false,
         max_age_seconds: 60 * 60,
@@ -0,0 +1,232 @@
+use iron::{IronResult, Request, Response};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use crate::ast::parse_resource;
+use crate::errors::Error;
+use crate::format::{create_bundle, format_variables, FormatRequest, Formatted};
+use crate::json;
+use crate::variables::{NumberOptions, Variable, Variables};
+
+/// The most combinations of variables one request may format.
+pub const MAX_ROWS: usize = 1000;
+
+fn default_step() -> f64 {
+    1.0
+}
+
+/// The values one variable takes in the matrix.
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum Dimension {
+    /// Numbers from `from` to `to`, inclusive.
+    Range {
+        from: f64,
+        to: f64,
+        #[serde(default = "default_step")]
+        step: f64,
+    },
+    /// Any values a variable can have, plain or typed.
+    Values(Vec<serde_json::Value>),
+}
+
+/// FTL to format with many sets of variables.
+#[derive(Debug, Deserialize)]
+pub struct MatrixRequest {
+    /// The FTL, and the variables which every combination starts from.
+    #[serde(flatten)]
+    request: FormatRequest,
+    /// Sets of variables to format with, each combined with the matrix.
+    #[serde(default)]
+    sets: Vec<Variables>,
+    /// Variables whose values are combined with each other.
+    #[serde(default)]
+    matrix: BTreeMap<String, Dimension>,
+}
+
+/// The messages formatted with one combination of variables.
+#[derive(Debug, Serialize)]
+struct Row {
+    variables: Variables,
+    #[serde(flatten)]
+    formatted: Formatted,
+}
+
+#[derive(Debug, Serialize)]
+struct Table {
+    rows: Vec<Row>,
+}
+
+fn too_many_rows() -> Error {
+    Error::InvalidRequest(format!(
+        "variables combine into more than {} sets",
+        MAX_ROWS
+    ))
+}
+
+impl Dimension {
+    fn values(self, name: &str) -> Result<Vec<Variable>, Error> {
+        let invalid =
+            |reason: String| Error::InvalidRequest(format!("matrix.{}: {}", name, reason));
+        match self {
+            Dimension::Range { from, to, step } => {
+                if step <= 0.0 {
+                    return Err(invalid("step must be greater than 0".to_string()));
+                }
+                if from > to {
+                    return Err(invalid("from must not be greater than to".to_string()));
+                }
+                // Compared before the cast, which would saturate huge or
+                // infinite counts, and let NaN through, instead of refusing them.
+                let count = ((to - from) / step + 1e-9).floor() + 1.0;
+                if !count.is_finite() || count > MAX_ROWS as f64 {
+                    return Err(too_many_rows());
+                }
+                let count = count as usize;
+                Ok((0..count)
+                    .map(|i| Variable::Number {
+                        value: from + i as f64 * step,
+                        options: NumberOptions::default(),
+                    })
+                    .collect())
+            }
+            Dimension::Values(values) => {
+                if values.is_empty() {
+                    return Err(invalid("must list at least one value".to_string()));
+                }
+                values
+                    .into_iter()
+                    .map(Variable::read)
+                    .collect::<Result<_, _>>()
+                    .map_err(invalid)
+            }
+        }
+    }
+}
+
+impl MatrixRequest {
+    /// Every combination of the sets and the matrix, each on top of the
+    /// request's own variables.
+    pub fn combinations(self) -> Result<(FormatRequest, Vec<Variables>), Error> {
+        if self.sets.len() > MAX_ROWS {
+            return Err(too_many_rows());
+        }
+        let base = &self.request.variables;
+        let mut combinations: Vec<Variables> = if self.sets.is_empty() {
+            vec![base.clone()]
+        } else {
+            self.sets
+                .into_iter()
+                .map(|set| {
+                    let mut variables = base.clone();
+                    variables.0.extend(set.0);
+                    variables
+                })
+                .collect()
+        };
+        for (name, dimension) in self.matrix {
+            let values = dimension.values(&name)?;
+            if combinations.len() * values.len() > MAX_ROWS {
+                return Err(too_many_rows());
+            }
+            let mut product = Vec::with_capacity(combinations.len() * values.len());
+            for variables in &combinations {
+                for value in &values {
+                    let mut variables = variables.clone();
+                    variables.0.insert(name.clone(), value.clone());
+                    product.push(variables);
+                }
+            }
+            combinations = product;
+        }
+        Ok((self.request, combinations))
+    }
+}
+
+fn format_matrix(request: MatrixRequest) -> Result<Table, Error> {
+    let (request, combinations) = request.combinations()?;
+    let bundle = create_bundle(request.langid()?, request.sources());
+    let (resource, _) = parse_resource(&request.messages);
+    let rows = combinations
+        .into_iter()
+        .map(|variables| {
+            let formatted = format_variables(&request, &resource, &bundle, &variables)?;
+            Ok(Row {
+                variables,
+                formatted,
+            })
+        })
+        .collect::<Result<_, Error>>()?;
+    Ok(Table { rows })
+}
+
+/// Format the FTL in the request with every combination of its variables.
+pub fn format(req: &mut Request) -> IronResult<Response> {
+    match json::read(req).and_then(format_matrix) {
+        Ok(table) => json::respond(table),
+        Err(err) => json::error(err.status(), err),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn values(dimension: serde_json::Value) -> Result<Vec<Variable>, Error> {
+        let dimension: Dimension = serde_json::from_value(dimension).unwrap();
+        dimension.values("n")
+    }
+
+    #[test]
+    fn counts_range_values() {
+        let values = values(json!({"from": 1, "to": 2, "step": 0.5})).unwrap();
+        assert_eq!(values.len(), 3);
+    }
+
+    #[test]
+    fn refuses_ranges_too_long_to_count() {
+        for to in &[1e300, f64::MAX] {
+            let error = values(json!({"from": 0, "to": to})).unwrap_err();
+            assert!(matches!(error, Error::InvalidRequest(_)));
+        }
+        let error = values(json!({"from": -1e308, "to": 1e308, "step": 1e-300})).unwrap_err();
+        assert!(matches!(error, Error::InvalidRequest(_)));
+    }
+
+    #[test]
+    fn refuses_dimensions_without_values() {
+        let error = values(json!([])).unwrap_err();
+        assert!(matches!(error, Error::InvalidRequest(_)));
+    }
+
+    fn request(sets: usize, matrix: serde_json::Value) -> MatrixRequest {
+        serde_json::from_value(json!({
+            "messages": "hello = Hello { $n }",
+            "sets": vec![json!({"n": 1}); sets],
+            "matrix": matrix,
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn combines_sets_with_the_matrix() {
+        let (_, combinations) = request(2, json!({"m": [1, 2, 3]})).combinations().unwrap();
+        assert_eq!(combinations.len(), 6);
+    }
+
+    #[test]
+    fn refuses_too_many_sets() {
+        let error = request(MAX_ROWS + 1, json!({})).combinations().unwrap_err();
+        assert!(matches!(error, Error::InvalidRequest(_)));
+        assert!(request(MAX_ROWS, json!({})).combinations().is_ok());
+    }
+
+    #[test]
+    fn refuses_too_many_combinations() {
+        let error = request(10, json!({"m": {"from": 1, "to": 101}}))
+            .combinations()
+            .unwrap_err();
+        assert!(matches!(error, Error::InvalidRequest(_)));
+    }
+}
@@ -1,31 +1,52 @@
-use hubcaps;
-use hyper;
-use hyper_tls;
-use iron;
 use std::sync::Arc;
 
-type Gists = hubcaps::gists::Gists<hyper_tls::HttpsConnector<hyper::client::HttpConnector>>;
+use crate::config::Config;
+use crate::store::PlaygroundStore;
 
 #[derive(Clone)]
-pub struct GistsMiddleware {
-    pub gists: Arc<Gists>,
+pub struct StoreMiddleware {
+    pub store: Arc<dyn PlaygroundStore>,
 }
 
-impl GistsMiddleware {
-    pub fn new(gists: Gists) -> Self {
-        GistsMiddleware {
-            gists: Arc::new(gists),
+impl StoreMiddleware {
+    pub fn new(store: impl PlaygroundStore + 'static) -> Self {
+        StoreMiddleware {
+            store: Arc::new(store),
         }
     }
 }
 
-impl iron::BeforeMiddleware for GistsMiddleware {
+impl iron::BeforeMiddleware for StoreMiddleware {
     fn before(&self, req: &mut iron::Request<'_, '_>) -> iron::IronResult<()> {
         req.extensions.insert::<Self>(self.clone());
         Ok(())
     }
 }
 
-impl iron::typemap::Key for GistsMiddleware {
+impl iron::typemap::Key for StoreMiddleware {
+    type Value = Self;
+}
+
+#[derive(Clone)]
+pub struct ConfigMiddleware {
+    pub config: Arc<Config>,
+}
+
+impl ConfigMiddleware {
+    pub fn new(config: Config) -> Self {
+        ConfigMiddleware {
+            config: Arc::new(config),
+        }
+    }
+}
+
+impl iron::BeforeMiddleware for ConfigMiddleware {
+    fn before(&self, req: &mut iron::Request<'_, '_>) -> iron::IronResult<()> {
+        req.extensions.insert::<Self>(self.clone());
+        Ok(())
+    }
+}
+
+impl iron::typemap::Key for ConfigMiddleware {
     type Value = Self;
 }
@@ -0,0 +1,219 @@
+use serde_json::json;
+use std::convert::TryFrom;
+
+use crate::setup::{Setup, RESERVED_FIELDS};
+
+/// The version of the shape of `setup.json` and `playground.json` written by
+/// this server.
+pub const SCHEMA_VERSION: u64 = 3;
+
+/// Upgrades from each version to the next, indexed by the version they
+/// upgrade from.
+const MIGRATIONS: &[fn(&mut serde_json::Value, &mut serde_json::Value)] =
+    &[v0_to_v1, v1_to_v2, v2_to_v3];
+
+/// Take the schema version out of a stored setup. Playgrounds saved before
+/// versioning was introduced are version 0.
+pub fn take_version(setup: &mut serde_json::Value) -> u64 {
+    setup
+        .as_object_mut()
+        .and_then(|setup| setup.remove("schema_version"))
+        .and_then(|version| version.as_u64())
+        .unwrap_or(0)
+}
+
+/// Record the current schema version in a setup about to be stored.
+pub fn with_version(setup: &serde_json::Value) -> serde_json::Value {
+    let mut setup = if setup.is_object() {
+        setup.clone()
+    } else {
+        json!({})
+    };
+    setup["schema_version"] = json!(SCHEMA_VERSION);
+    setup
+}
+
+/// Upgrade the setup and variables of a playground stored with schema
+/// `version` to the current schema.
+pub fn migrate(version: u64, setup: &mut serde_json::Value, variables: &mut serde_json::Value) {
+    for migration in MIGRATIONS.iter().skip(version as usize) {
+        migration(setup, variables);
+    }
+}
+
+/// Unversioned playgrounds were saved by clients which didn't always send
+/// `visible`, `locale` or `dir`, and sometimes sent `null` variables.
+fn v0_to_v1(setup: &mut serde_json::Value, variables: &mut serde_json::Value) {
+    if !setup.is_object() {
+        *setup = json!({});
+    }
+    if !setup["visible"].is_array() {
+        setup["visible"] = json!(["messages", "output"]);
+    }
+    if !setup["locale"].is_string() {
+        setup["locale"] = json!("en-US");
+    }
+    if !setup["dir"].is_string() {
+        setup["dir"] = json!("ltr");
+    }
+    if !variables.is_object() {
+        *variables = json!({});
+    }
+}
+
+/// Setups weren't validated before version 2. Fields which `Setup` would
+/// refuse are dropped so that they fall back to their defaults, except for a
+/// `dir` which only differs in case.
+fn v1_to_v2(setup: &mut serde_json::Value, _variables: &mut serde_json::Value) {
+    let fields = match setup.as_object_mut() {
+        Some(fields) => fields,
+        None => return,
+    };
+    if let Some(dir) = fields.get("dir").and_then(|dir| dir.as_str()) {
+        let dir = dir.to_lowercase();
+        fields.insert("dir".to_string(), json!(dir));
+    }
+    let invalid: Vec<String> = fields
+        .iter()
+        .filter(|(name, _)| !RESERVED_FIELDS.contains(&name.as_str()))
+        .filter(|(name, value)| Setup::try_from(json!({ *name: value })).is_err())
+        .map(|(name, _)| name.clone())
+        .collect();
+    for name in invalid {
+        fields.remove(&name);
+    }
+}
+
+/// Before variables were typed, any JSON value could be stored. Strings and
+/// numbers are still read as they are; anything else is kept as its text.
+fn v2_to_v3(_setup: &mut serde_json::Value, variables: &mut serde_json::Value) {
+    if let Some(variables) = variables.as_object_mut() {
+        for value in variables.values_mut() {
+            if !value.is_string() && !value.is_number() {
+                *value = json!(value.to_string());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn load(setup: &str, variables: &str) -> (serde_json::Value, serde_json::Value) {
+        let mut setup = serde_json::from_str(setup).unwrap();
+        let mut variables = serde_json::from_str(variables).unwrap();
+        let version = take_version(&mut setup);
+        migrate(version, &mut setup, &mut variables);
+        (setup, variables)
+    }
+
+    // The fixtures were saved by the unversioned server, which wrote the
+    // setup and variables sent by the client with `to_string_pretty`.
+
+    #[test]
+    fn keeps_complete_unversioned_setup() {
+        let (setup, variables) = load(
+            include_str!("fixtures/v0-defaults/setup.json"),
+            include_str!("fixtures/v0-defaults/playground.json"),
+        );
+        assert_eq!(
+            setup,
+            json!({
+                "visible": ["messages", "output"],
+                "locale": "en-US",
+                "dir": "ltr",
+            })
+        );
+        assert_eq!(
+            variables,
+            json!({ "userName": "Anne", "userGender": "female", "photoCount": 3 })
+        );
+    }
+
+    #[test]
+    fn upgrades_setup_without_dir() {
+        let (setup, variables) = load(
+            include_str!("fixtures/v0-no-dir/setup.json"),
+            include_str!("fixtures/v0-no-dir/playground.json"),
+        );
+        assert_eq!(
+            setup,
+            json!({
+                "visible": ["messages", "config", "output"],
+                "locale": "pl",
+                "dir": "ltr",
+            })
+        );
+        assert_eq!(variables["photoCount"], json!(22));
+    }
+
+    #[test]
+    fn upgrades_setup_without_visible() {
+        let (setup, _) = load(
+            include_str!("fixtures/v0-no-visible/setup.json"),
+            include_str!("fixtures/v0-no-visible/playground.json"),
+        );
+        assert_eq!(
+            setup,
+            json!({
+                "visible": ["messages", "output"],
+                "locale": "ar",
+                "dir": "rtl",
+            })
+        );
+    }
+
+    #[test]
+    fn upgrades_empty_setup_and_null_variables() {
+        let (setup, variables) = load("{}", "null");
+        assert_eq!(setup["locale"], json!("en-US"));
+        assert_eq!(setup["dir"], json!("ltr"));
+        assert_eq!(variables, json!({}));
+    }
+
+    #[test]
+    fn keeps_untyped_variables_as_text() {
+        let (_, variables) = load(
+            r#"{"schema_version": 2}"#,
+            r#"{"name": "Anne", "count": 3, "admin": true, "tags": ["a"]}"#,
+        );
+        assert_eq!(
+            variables,
+            json!({ "name": "Anne", "count": 3, "admin": "true", "tags": "[\"a\"]" })
+        );
+    }
+
+    #[test]
+    fn leaves_current_version_alone() {
+        let setup = json!({ "visible": ["ast"], "locale": "ar", "dir": "rtl" });
+        let stored = with_version(&setup).to_string();
+        let (loaded, variables) = load(&stored, r#"{"userName": "Anne"}"#);
+        assert_eq!(loaded, setup);
+        assert_eq!(variables, json!({ "userName": "Anne" }));
+    }
+
+    #[test]
+    fn repairs_setup_saved_before_validation() {
+        let stored = json!({
+            "schema_version": 1,
+            "visible": "all",
+            "locale": "en US",
+            "dir": "RTL",
+            "fallbacks": ["pl", "de DE"],
+            "theme": "dark",
+        });
+        let (setup, _) = load(&stored.to_string(), "{}");
+        assert_eq!(setup, json!({ "dir": "rtl", "theme": "dark" }));
+        let setup = Setup::try_from(setup).unwrap();
+        assert_eq!(setup.visible, ["messages", "output"]);
+        assert_eq!(setup.locale, "en-US");
+    }
+
+    #[test]
+    fn keeps_fields_reserved_for_stores() {
+        let mut setup = json!({ "resources": ["brand.ftl"], "dir": "sideways" });
+        migrate(1, &mut setup, &mut json!({}));
+        assert_eq!(setup, json!({ "resources": ["brand.ftl"] }));
+    }
+}
@@ -1,139 +1,416 @@
-use hubcaps::gists;
-use iron::{status, IronResult, Request, Response};
+use iron::{
+    headers::{Authorization, Bearer},
+    status, IronResult, Request, Response,
+};
 use router::Router;
 use serde::{Deserialize, Serialize};
-use serde_json;
-use std::collections::HashMap;
-use std::convert::TryFrom;
-use std::io::Read;
-use tokio::runtime::Runtime;
+use std::collections::HashSet;
+use std::iter;
+use unic_langid::LanguageIdentifier;
 
+use crate::config::Config;
 use crate::errors::Error;
 use crate::json;
-use crate::middleware::GistsMiddleware;
+use crate::middleware::{ConfigMiddleware, StoreMiddleware};
+use crate::serialize::canonical;
+use crate::setup::Setup;
+use crate::token;
+use crate::variables::Variables;
+
+/// The file which holds a playground's main messages.
+pub const MESSAGES_FILE: &str = "playground.ftl";
+
+/// An additional FTL file in a playground.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Resource {
+    pub name: String,
+    pub content: String,
+}
+
+/// The FTL for one of a playground's other locales.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Translation {
+    pub locale: String,
+    pub messages: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub resources: Vec<Resource>,
+}
 
 #[derive(Debug, Serialize, Deserialize)]
-struct Playground {
-    id: Option<String>,
-    messages: String,
-    variables: serde_json::Value,
-    setup: serde_json::Value,
+pub struct Playground {
+    pub id: Option<String>,
+    /// The name of the gist file the messages were read from.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub filename: Option<String>,
+    pub messages: String,
+    /// FTL files which are added to the bundle after `messages`, in order.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub resources: Vec<Resource>,
+    /// The FTL of other locales. `setup.fallbacks` lists the locales to try,
+    /// in order, when a message is missing from `setup.locale`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub translations: Vec<Translation>,
+    pub variables: Variables,
+    pub setup: Setup,
+}
+
+/// The response to creating a playground, which is the only time its edit
+/// token is revealed.
+#[derive(Debug, Serialize)]
+struct Created {
+    #[serde(flatten)]
+    playground: Playground,
+    edit_token: String,
+}
+
+pub fn sources<'a>(
+    messages: &'a str,
+    resources: &'a [Resource],
+) -> impl Iterator<Item = (&'a str, &'a str)> {
+    iter::once((MESSAGES_FILE, messages)).chain(
+        resources
+            .iter()
+            .map(|resource| (resource.name.as_str(), resource.content.as_str())),
+    )
+}
+
+/// The name under which a translation's file is stored, e.g.
+/// `playground.pl.ftl`.
+pub fn localized_filename(name: &str, locale: &str) -> String {
+    format!("{}.{}.ftl", name.trim_end_matches(".ftl"), locale)
+}
+
+impl Translation {
+    /// The translation's FTL sources and their filenames, in the order in
+    /// which they should be added to a bundle.
+    pub fn sources(&self) -> impl Iterator<Item = (&str, &str)> {
+        sources(&self.messages, &self.resources)
+    }
+}
+
+impl Playground {
+    /// The playground's FTL sources and their filenames, in the order in
+    /// which they should be added to a bundle.
+    pub fn sources(&self) -> impl Iterator<Item = (&str, &str)> {
+        sources(&self.messages, &self.resources)
+    }
+
+    /// The locale of `messages`.
+    pub fn locale(&self) -> &str {
+        &self.setup.locale
+    }
+
+    /// Every FTL file of the playground, named as it is stored.
+    pub fn files(&self) -> Vec<(String, &str)> {
+        let mut files: Vec<(String, &str)> = self
+            .sources()
+            .map(|(name, content)| (name.to_string(), content))
+            .collect();
+        for translation in &self.translations {
+            files.extend(
+                translation.sources().map(|(name, content)| {
+                    (localized_filename(name, &translation.locale), content)
+                }),
+            );
+        }
+        files
+    }
+
+    /// Rewrite every FTL file of the playground in canonical form, keeping
+    /// Junk as it was written.
+    pub fn canonicalize(&mut self) {
+        let sources = iter::once(&mut self.messages)
+            .chain(
+                self.resources
+                    .iter_mut()
+                    .map(|resource| &mut resource.content),
+            )
+            .chain(self.translations.iter_mut().flat_map(|translation| {
+                iter::once(&mut translation.messages).chain(
+                    translation
+                        .resources
+                        .iter_mut()
+                        .map(|resource| &mut resource.content),
+                )
+            }));
+        for source in sources {
+            *source = canonical(source, true);
+        }
+    }
+
+    /// Check the parts of a playground sent by a client before storing it.
+    fn validate(&self) -> Result<(), Error> {
+        if self.messages.trim().is_empty() {
+            return Err(Error::InvalidPayload(
+                "messages must not be empty".to_string(),
+            ));
+        }
+        validate_resources(&self.resources)?;
+
+        let mut locales = HashSet::new();
+        locales.insert(self.locale());
+        for translation in &self.translations {
+            if translation.locale.parse::<LanguageIdentifier>().is_err() {
+                return Err(Error::InvalidPayload(format!(
+                    "invalid translation locale: {}",
+                    translation.locale
+                )));
+            }
+            if !locales.insert(&translation.locale) {
+                return Err(Error::InvalidPayload(format!(
+                    "duplicate locale: {}",
+                    translation.locale
+                )));
+            }
+            validate_resources(&translation.resources)?;
+        }
+
+        let mut names = HashSet::new();
+        for (name, _) in self.files() {
+            if !names.insert(name.clone()) {
+                return Err(Error::InvalidPayload(format!(
+                    "more than one file would be stored as {}",
+                    name
+                )));
+            }
+        }
+
+        for locale in &self.setup.fallbacks {
+            if !locales.contains(locale.as_str()) {
+                return Err(Error::InvalidPayload(format!(
+                    "fallback locale has no translation: {}",
+                    locale
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+fn validate_resources(resources: &[Resource]) -> Result<(), Error> {
+    let mut names = HashSet::new();
+    for resource in resources {
+        if !is_valid_resource_name(&resource.name) {
+            return Err(Error::InvalidPayload(format!(
+                "invalid resource name: {}",
+                resource.name
+            )));
+        }
+        if !names.insert(&resource.name) {
+            return Err(Error::InvalidPayload(format!(
+                "duplicate resource name: {}",
+                resource.name
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// Resources are stored as files next to `playground.ftl`, so their names
+/// must be plain `.ftl` filenames.
+pub fn is_valid_resource_name(name: &str) -> bool {
+    name.ends_with(".ftl")
+        && name != MESSAGES_FILE
+        && !name.starts_with('.')
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '.' || c == '-' || c == '_')
 }
 
 pub fn get(req: &mut Request) -> IronResult<Response> {
-    let gists_middleware = req.extensions.get::<GistsMiddleware>().unwrap();
-    let gists = &gists_middleware.gists;
+    let store = &req.extensions.get::<StoreMiddleware>().unwrap().store;
     let params = req.extensions.get::<Router>().unwrap();
     let id = params.find("id").expect("No route parameter called id");
-    let mut rt = match Runtime::new() {
-        Ok(rt) => rt,
-        Err(_) => return json::error(status::ServiceUnavailable, Error::Runtime),
-    };
-    let gist = match rt.block_on(gists.get(id)) {
-        Ok(gist) => gist,
-        Err(hubcaps::errors::Error(hubcaps::errors::ErrorKind::Fault { code, .. }, _))
-            if code == 404 =>
-        {
-            return json::error(status::NotFound, Error::NotFound)
-        }
-        Err(_) => return json::error(status::InternalServerError, Error::Fetching),
-    };
-    match Playground::try_from(gist) {
+    match store.get(id) {
         Ok(playground) => json::respond(playground),
-        Err(err) => json::error(status::InternalServerError, err),
+        Err(err) => json::error(err.status(), err),
     }
 }
 
+/// Read and validate a playground from the request body.
+pub fn read_payload(req: &mut Request) -> Result<Playground, Error> {
+    let playground: Playground = json::read(req).map_err(|err| match err {
+        Error::InvalidRequest(reason) => Error::InvalidPayload(reason),
+        err => err,
+    })?;
+    playground.validate()?;
+    Ok(playground)
+}
+
 pub fn create(req: &mut Request) -> IronResult<Response> {
-    let gists_middleware = req.extensions.get::<GistsMiddleware>().unwrap();
-    let gists = &gists_middleware.gists;
-    let mut payload = String::new();
-    if req.body.read_to_string(&mut payload).is_err() {
-        return json::error(status::InternalServerError, Error::ReadingRequest);
-    }
-    let playground = match serde_json::from_str::<Playground>(&payload) {
+    let store = req
+        .extensions
+        .get::<StoreMiddleware>()
+        .unwrap()
+        .store
+        .clone();
+    let mut playground = match read_payload(req) {
         Ok(playground) => playground,
-        Err(_) => return json::error(status::InternalServerError, Error::Deserializing),
+        Err(err) => return json::error(err.status(), err),
     };
-    let options = match gists::GistOptions::try_from(playground) {
-        Ok(options) => options,
-        Err(err) => return json::error(status::InternalServerError, err),
+    playground.canonicalize();
+    match store.create(playground) {
+        Ok(playground) => respond_created(req, playground),
+        Err(err) => json::error(err.status(), err),
+    }
+}
+
+fn respond_created(req: &Request, playground: Playground) -> IronResult<Response> {
+    let config = &req.extensions.get::<ConfigMiddleware>().unwrap().config;
+    let id = playground.id.clone().unwrap_or_default();
+    json::respond(Created {
+        edit_token: token::issue(&config.edit_token_secret, &id),
+        playground,
+    })
+}
+
+/// Check that the request carries the edit token for the playground `id`, or
+/// the admin token if one is configured.
+fn authorize(req: &Request, id: &str) -> Result<(), Error> {
+    let config = &req.extensions.get::<ConfigMiddleware>().unwrap().config;
+    let token = req
+        .headers
+        .get::<Authorization<Bearer>>()
+        .map(|Authorization(Bearer { token })| token.as_str());
+    check_token(config, id, token)
+}
+
+fn check_token(config: &Config, id: &str, token: Option<&str>) -> Result<(), Error> {
+    let token = match token {
+        Some(token) => token,
+        None => return Err(Error::Unauthorized),
     };
-    let mut rt = match Runtime::new() {
-        Ok(rt) => rt,
-        Err(_) => return json::error(status::ServiceUnavailable, Error::Runtime),
+    let is_admin = match config.admin_token {
+        Some(ref admin_token) => token::eq(admin_token, token),
+        None => false,
     };
-    let gist = match rt.block_on(gists.create(&options)) {
-        Ok(gist) => gist,
-        Err(_) => return json::error(status::InternalServerError, Error::Creating),
+    if is_admin || token::verify(&config.edit_token_secret, id, token) {
+        Ok(())
+    } else {
+        Err(Error::Unauthorized)
+    }
+}
+
+pub fn update(req: &mut Request) -> IronResult<Response> {
+    let store = req
+        .extensions
+        .get::<StoreMiddleware>()
+        .unwrap()
+        .store
+        .clone();
+    let params = req.extensions.get::<Router>().unwrap();
+    let id = params
+        .find("id")
+        .expect("No route parameter called id")
+        .to_string();
+    if let Err(err) = authorize(req, &id) {
+        return json::error(err.status(), err);
+    }
+    let mut playground = match read_payload(req) {
+        Ok(playground) => playground,
+        Err(err) => return json::error(err.status(), err),
     };
-    match Playground::try_from(gist) {
+    playground.canonicalize();
+    match store.update(&id, playground) {
+        Ok(playground) => json::respond(playground),
+        Err(err) => json::error(err.status(), err),
+    }
+}
+
+pub fn delete(req: &mut Request) -> IronResult<Response> {
+    let store = &req.extensions.get::<StoreMiddleware>().unwrap().store;
+    let params = req.extensions.get::<Router>().unwrap();
+    let id = params.find("id").expect("No route parameter called id");
+    if let Err(err) = authorize(req, id) {
+        return json::error(err.status(), err);
+    }
+    match store.delete(id) {
+        Ok(()) => Ok(Response::with(status::NoContent)),
+        Err(err) => json::error(err.status(), err),
+    }
+}
+
+pub fn fork(req: &mut Request) -> IronResult<Response> {
+    let store = &req.extensions.get::<StoreMiddleware>().unwrap().store;
+    let params = req.extensions.get::<Router>().unwrap();
+    let id = params.find("id").expect("No route parameter called id");
+    match store.fork(id) {
+        Ok(playground) => respond_created(req, playground),
+        Err(err) => json::error(err.status(), err),
+    }
+}
+
+pub fn revisions(req: &mut Request) -> IronResult<Response> {
+    let store = &req.extensions.get::<StoreMiddleware>().unwrap().store;
+    let params = req.extensions.get::<Router>().unwrap();
+    let id = params.find("id").expect("No route parameter called id");
+    match store.revisions(id) {
+        Ok(revisions) => json::respond(revisions),
+        Err(err) => json::error(err.status(), err),
+    }
+}
+
+pub fn revision(req: &mut Request) -> IronResult<Response> {
+    let store = &req.extensions.get::<StoreMiddleware>().unwrap().store;
+    let params = req.extensions.get::<Router>().unwrap();
+    let id = params.find("id").expect("No route parameter called id");
+    let rev = params.find("rev").expect("No route parameter called rev");
+    match store.revision(id, rev) {
         Ok(playground) => json::respond(playground),
-        Err(err) => json::error(status::InternalServerError, err),
-    }
-}
-
-fn try_file_content<'gist>(gist: &'gist gists::Gist, name: &str) -> Result<&'gist String, Error> {
-    gist.files
-        .get(name)
-        .ok_or_else(|| Error::MissingFile(name.to_string()))?
-        .content
-        .as_ref()
-        .ok_or_else(|| Error::EmptyFile(name.to_string()))
-}
-
-fn try_deserialize_json<'gist>(
-    gist: &'gist gists::Gist,
-    name: &str,
-) -> Result<serde_json::value::Value, Error> {
-    serde_json::from_str(try_file_content(&gist, name)?).or(Err(Error::Deserializing))
-}
-
-impl TryFrom<gists::Gist> for Playground {
-    type Error = Error;
-    fn try_from(gist: gists::Gist) -> Result<Self, Self::Error> {
-        Ok(Playground {
-            id: Some(gist.id.clone()),
-            messages: try_file_content(&gist, "playground.ftl")?.clone(),
-            variables: try_deserialize_json(&gist, "playground.json")?,
-            setup: try_deserialize_json(&gist, "setup.json")?,
-        })
-    }
-}
-
-fn try_serialize_json(value: &serde_json::value::Value) -> Result<String, Error> {
-    serde_json::ser::to_string_pretty(value).or(Err(Error::Serializing))
-}
-
-impl TryFrom<Playground> for gists::GistOptions {
-    type Error = Error;
-    fn try_from(playground: Playground) -> Result<Self, Self::Error> {
-        let mut files = HashMap::new();
-        files.insert(
-            "playground.ftl".to_string(),
-            gists::Content {
-                filename: None,
-                content: playground.messages,
-            },
-        );
-        files.insert(
-            "playground.json".to_string(),
-            gists::Content {
-                filename: None,
-                content: try_serialize_json(&playground.variables)?,
-            },
-        );
-        files.insert(
-            "setup.json".to_string(),
-            gists::Content {
-                filename: None,
-                content: try_serialize_json(&playground.setup)?,
-            },
-        );
-        Ok(gists::GistOptions {
-            description: Some("A Fluent Playground snippet".to_string()),
-            public: Some(true),
-            files,
-        })
+        Err(err) => json::error(err.status(), err),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> Config {
+        Config {
+            max_payload_size: 1024,
+            max_file_size: 1024,
+            edit_token_secret: "secret".to_string(),
+            admin_token: Some("admin".to_string()),
+        }
+    }
+
+    fn is_unauthorized(result: Result<(), Error>) -> bool {
+        matches!(result, Err(Error::Unauthorized))
+    }
+
+    #[test]
+    fn accepts_the_edit_token_of_the_playground() {
+        let token = token::issue("secret", "abc");
+        assert!(check_token(&config(), "abc", Some(&token)).is_ok());
+    }
+
+    #[test]
+    fn refuses_missing_and_wrong_tokens() {
+        let config = config();
+        assert!(is_unauthorized(check_token(&config, "abc", None)));
+        assert!(is_unauthorized(check_token(&config, "abc", Some("xyz"))));
+        let other = token::issue("secret", "def");
+        assert!(is_unauthorized(check_token(&config, "abc", Some(&other))));
+        let forged = token::issue("other secret", "abc");
+        assert!(is_unauthorized(check_token(&config, "abc", Some(&forged))));
+    }
+
+    #[test]
+    fn accepts_the_admin_token_for_any_playground() {
+        let config = config();
+        assert!(check_token(&config, "abc", Some("admin")).is_ok());
+        assert!(check_token(&config, "def", Some("admin")).is_ok());
+        assert!(is_unauthorized(check_token(&config, "abc", Some("admi"))));
+        assert!(is_unauthorized(check_token(&config, "abc", Some("Admin"))));
+    }
+
+    #[test]
+    fn refuses_the_admin_token_when_none_is_configured() {
+        let config = Config {
+            admin_token: None,
+            ..config()
+        };
+        assert!(is_unauthorized(check_token(&config, "abc", Some("admin"))));
+        assert!(is_unauthorized(check_token(&config, "abc", Some(""))));
     }
 }
@@ -0,0 +1,80 @@
+use fluent_syntax::serializer::{self, Options};
+use iron::{IronResult, Request, Response};
+use serde::{Deserialize, Serialize};
+
+use crate::ast::parse_resource;
+use crate::json;
+
+fn default_with_junk() -> bool {
+    true
+}
+
+/// FTL sent to be rewritten in canonical form.
+#[derive(Debug, Deserialize)]
+struct Source {
+    messages: String,
+    /// Whether to keep Junk as it was written, rather than dropping it.
+    #[serde(default = "default_with_junk")]
+    with_junk: bool,
+}
+
+#[derive(Debug, Serialize)]
+struct Serialized {
+    messages: String,
+}
+
+/// Parse FTL and write it back out in canonical form, with consistent
+/// indentation, variant layout and blank lines.
+pub fn canonical(source: &str, with_junk: bool) -> String {
+    let (resource, _) = parse_resource(source);
+    serializer::serialize_with_options(&resource, Options { with_junk })
+}
+
+pub fn serialize(req: &mut Request) -> IronResult<Response> {
+    match json::read::<Source>(req) {
+        Ok(source) => json::respond(Serialized {
+            messages: canonical(&source.messages, source.with_junk),
+        }),
+        Err(err) => json::error(err.status(), err),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rewrites_ftl_in_canonical_form() {
+        let source =
+            "hello =   Hello\n\n\n\nemails = {$n ->\n[one] One email\n   *[other] {$n} emails\n}\n";
+        assert_eq!(
+            canonical(source, true),
+            "\
+hello = Hello
+emails =
+    { $n ->
+        [one] One email
+       *[other] { $n } emails
+    }
+"
+        );
+    }
+
+    #[test]
+    fn keeps_junk_by_default() {
+        let source: Source = serde_json::from_str(r#"{"messages": "hello = Hello"}"#).unwrap();
+        assert!(source.with_junk);
+        assert_eq!(
+            canonical("broken = }\nhello =  Hello\n", source.with_junk),
+            "broken = }\nhello = Hello\n"
+        );
+    }
+
+    #[test]
+    fn drops_junk_when_asked() {
+        assert_eq!(
+            canonical("broken = }\nhello =  Hello\n", false),
+            "hello = Hello\n"
+        );
+    }
+}
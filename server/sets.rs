@@ -0,0 +1,198 @@
+use intl_pluralrules::PluralRules;
+use iron::{IronResult, Request, Response};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashSet};
+
+use crate::ast::parse_resource;
+use crate::errors::Error;
+use crate::explain::{category_name, explain, plural_rules};
+use crate::format::{create_bundle, fluent_args, FormatRequest};
+use crate::infer::{infer, skeleton, InferredVariable, PLURAL_CATEGORIES};
+use crate::json;
+use crate::matrix::MAX_ROWS;
+use crate::playground::sources;
+use crate::variables::{NumberOptions, Variable, Variables};
+
+/// Numbers tried when looking for one in each plural category: integers
+/// first, then the powers of ten some locales single out, then fractions.
+fn candidate_numbers() -> impl Iterator<Item = f64> {
+    let integers = (0..=200).map(f64::from);
+    let powers = [1e3, 1e4, 1e5, 1e6].iter().cloned();
+    let fractions = (0..100).map(|tenths| f64::from(tenths) / 10.0 + 0.5);
+    integers.chain(powers).chain(fractions)
+}
+
+/// The smallest number in each plural category of the locale, in the order
+/// of `PLURAL_CATEGORIES`. Numbers in `keys` are skipped, since variants
+/// with those keys would catch them before any plural category does.
+fn plural_examples(rules: &PluralRules, keys: &[f64]) -> Vec<f64> {
+    let mut examples: BTreeMap<usize, f64> = BTreeMap::new();
+    for number in candidate_numbers().filter(|number| !keys.contains(number)) {
+        let category = match rules.select(number) {
+            Ok(category) => category_name(category),
+            Err(_) => continue,
+        };
+        let order = PLURAL_CATEGORIES
+            .iter()
+            .position(|name| *name == category)
+            .expect("Every plural category is known");
+        examples.entry(order).or_insert(number);
+    }
+    examples.values().cloned().collect()
+}
+
+fn number(value: f64) -> Variable {
+    Variable::Number {
+        value,
+        options: NumberOptions::default(),
+    }
+}
+
+/// The values of a selected variable which may reach each of its variants:
+/// a number in each plural category and each number key if the variable is
+/// a number, or else each key.
+fn selector_values(variable: &InferredVariable, rules: Option<&PluralRules>) -> Vec<Variable> {
+    if !variable.is_number() {
+        return variable
+            .keys
+            .iter()
+            .map(|key| Variable::String { value: key.clone() })
+            .collect();
+    }
+    let keys: Vec<f64> = variable
+        .keys
+        .iter()
+        .filter_map(|key| key.parse().ok())
+        .collect();
+    let plural = rules.map_or_else(Vec::new, |rules| plural_examples(rules, &keys));
+    plural.into_iter().chain(keys).map(number).collect()
+}
+
+/// Every combination of the values, or if there would be too many, as many
+/// sets as the longest list of values, taking the values in turn.
+fn candidates(base: &Variables, values: &[(String, Vec<Variable>)]) -> Vec<Variables> {
+    let product: usize = values.iter().map(|(_, values)| values.len()).product();
+    if product > MAX_ROWS {
+        let longest = values.iter().map(|(_, values)| values.len()).max();
+        return (0..longest.unwrap_or(1))
+            .map(|i| {
+                let mut variables = base.clone();
+                for (name, values) in values {
+                    variables
+                        .0
+                        .insert(name.clone(), values[i % values.len()].clone());
+                }
+                variables
+            })
+            .collect();
+    }
+    let mut candidates = vec![base.clone()];
+    for (name, values) in values {
+        let mut next = Vec::with_capacity(candidates.len() * values.len());
+        for variables in &candidates {
+            for value in values {
+                let mut variables = variables.clone();
+                variables.0.insert(name.clone(), value.clone());
+                next.push(variables);
+            }
+        }
+        candidates = next;
+    }
+    candidates
+}
+
+#[derive(Debug, Serialize)]
+struct Sets {
+    sets: Vec<Variables>,
+}
+
+/// Find a few sets of variables which between them reach every variant of
+/// the messages which can be reached. Candidates are tried in order, and a
+/// set is kept when it reaches a variant the sets before it haven't.
+fn generate(request: FormatRequest) -> Result<Sets, Error> {
+    let variables = infer(sources(&request.messages, &request.resources));
+    let mut base = skeleton(&variables);
+    base.0.extend(request.variables.0.clone());
+
+    let rules = plural_rules(request.langid()?);
+    // Only selected variables have keys, including those selected through
+    // `NUMBER()` or `DATETIME()`; the others keep their example value.
+    let values: Vec<(String, Vec<Variable>)> = variables
+        .iter()
+        .filter(|(_, variable)| !variable.keys.is_empty())
+        .map(|(name, variable)| (name.clone(), selector_values(variable, rules.as_ref())))
+        .filter(|(_, values)| !values.is_empty())
+        .collect();
+
+    let bundle = create_bundle(request.langid()?, request.sources());
+    let (resource, _) = parse_resource(&request.messages);
+    let mut reached = HashSet::new();
+    let mut sets = Vec::new();
+    for candidate in candidates(&base, &values) {
+        let selections = explain(
+            &request.messages,
+            &resource,
+            &bundle,
+            &fluent_args(&candidate),
+            request.langid()?,
+        );
+        let mut reaches_new = false;
+        for selection in selections {
+            if let Some(index) = selection.variant {
+                reaches_new |= reached.insert((selection.range.start, index));
+            }
+        }
+        if reaches_new || sets.is_empty() {
+            sets.push(candidate);
+        }
+    }
+    Ok(Sets { sets })
+}
+
+/// Generate variable sets reaching every variant of the request's messages.
+pub fn post(req: &mut Request) -> IronResult<Response> {
+    match json::read(req).and_then(generate) {
+        Ok(sets) => json::respond(sets),
+        Err(err) => json::error(err.status(), err),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use unic_langid::LanguageIdentifier;
+
+    #[test]
+    fn reaches_plural_variants_of_number_selectors() {
+        let messages = "photos = { NUMBER($n) ->\n    [one] one\n    [few] few\n    [many] many\n   *[other] other\n}\n";
+        let request: FormatRequest =
+            serde_json::from_value(json!({"messages": messages, "locale": "pl"})).unwrap();
+        let sets = generate(request).unwrap().sets;
+
+        let locale: LanguageIdentifier = "pl".parse().unwrap();
+        let bundle = create_bundle(locale.clone(), std::iter::once(messages));
+        let (resource, _) = parse_resource(messages);
+        let selected: HashSet<String> = sets
+            .iter()
+            .flat_map(|set| {
+                explain(
+                    messages,
+                    &resource,
+                    &bundle,
+                    &fluent_args(set),
+                    locale.clone(),
+                )
+            })
+            .filter_map(|selection| selection.selected)
+            .collect();
+        for key in &["one", "few", "many", "other"] {
+            assert!(
+                selected.contains(*key),
+                "{} isn't reached by {:?}",
+                key,
+                sets
+            );
+        }
+    }
+}
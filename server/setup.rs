@@ -0,0 +1,142 @@
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::convert::TryFrom;
+use unic_langid::LanguageIdentifier;
+
+/// The direction in which the output is displayed.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Dir {
+    Ltr,
+    Rtl,
+    Auto,
+}
+
+/// How the client should present a playground.
+///
+/// Setups are checked field by field as they are deserialized, so that an
+/// invalid one is refused with an error naming the offending field.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(try_from = "serde_json::Value")]
+pub struct Setup {
+    /// The panels shown in the client.
+    pub visible: Vec<String>,
+    /// The locale of the playground's messages, as a BCP 47 tag.
+    pub locale: String,
+    pub dir: Dir,
+    /// The locales to try, in order, when a message is missing from `locale`.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub fallbacks: Vec<String>,
+    /// The id of the playground this one was forked from.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub parent: Option<String>,
+    /// Fields this server doesn't know about, kept as they were sent. The
+    /// fields stores record in a setup are refused.
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
+}
+
+impl Default for Setup {
+    fn default() -> Self {
+        Setup {
+            visible: vec!["messages".to_string(), "output".to_string()],
+            locale: "en-US".to_string(),
+            dir: Dir::Ltr,
+            fallbacks: Vec::new(),
+            parent: None,
+            extra: serde_json::Map::new(),
+        }
+    }
+}
+
+/// Fields which stores record in a stored setup themselves, and so can't be
+/// sent by a client.
+pub const RESERVED_FIELDS: &[&str] = &["resources", "translations", "schema_version"];
+
+fn is_valid_locale(locale: &str) -> bool {
+    locale.parse::<LanguageIdentifier>().is_ok()
+}
+
+/// Take a field out of a setup, if it is there, describing what was expected
+/// of it if it doesn't deserialize.
+fn take_field<T: DeserializeOwned>(
+    fields: &mut serde_json::Map<String, serde_json::Value>,
+    name: &str,
+    expected: &str,
+) -> Result<Option<T>, String> {
+    match fields.remove(name) {
+        Some(value) => serde_json::from_value(value)
+            .map(Some)
+            .map_err(|_| format!("setup.{} must be {}", name, expected)),
+        None => Ok(None),
+    }
+}
+
+impl TryFrom<serde_json::Value> for Setup {
+    type Error = String;
+
+    fn try_from(value: serde_json::Value) -> Result<Self, Self::Error> {
+        let mut fields = match value {
+            serde_json::Value::Object(fields) => fields,
+            _ => return Err("setup must be an object".to_string()),
+        };
+        if let Some(name) = RESERVED_FIELDS
+            .iter()
+            .find(|name| fields.contains_key(**name))
+        {
+            return Err(format!("setup.{} is reserved for the server", name));
+        }
+        let default = Setup::default();
+
+        let visible =
+            take_field(&mut fields, "visible", "a list of panel names")?.unwrap_or(default.visible);
+        let locale: String =
+            take_field(&mut fields, "locale", "a string")?.unwrap_or(default.locale);
+        if !is_valid_locale(&locale) {
+            return Err(format!(
+                "setup.locale must be a BCP 47 language tag, not {:?}",
+                locale
+            ));
+        }
+        let dir = take_field(&mut fields, "dir", "one of \"ltr\", \"rtl\" or \"auto\"")?
+            .unwrap_or(default.dir);
+        let fallbacks: Vec<String> =
+            take_field(&mut fields, "fallbacks", "a list of locales")?.unwrap_or_default();
+        if let Some(locale) = fallbacks.iter().find(|locale| !is_valid_locale(locale)) {
+            return Err(format!(
+                "setup.fallbacks must only hold BCP 47 language tags, not {:?}",
+                locale
+            ));
+        }
+        let parent = take_field(&mut fields, "parent", "a playground id")?;
+
+        Ok(Setup {
+            visible,
+            locale,
+            dir,
+            fallbacks,
+            parent,
+            extra: fields,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn refuses_fields_reserved_for_stores() {
+        for name in RESERVED_FIELDS {
+            let err = Setup::try_from(json!({ "locale": "en-US", *name: [] })).unwrap_err();
+            assert_eq!(err, format!("setup.{} is reserved for the server", name));
+        }
+    }
+
+    #[test]
+    fn keeps_unknown_fields() {
+        let setup = Setup::try_from(json!({ "theme": "dark" })).unwrap();
+        assert_eq!(setup.locale, "en-US");
+        assert_eq!(setup.extra["theme"], json!("dark"));
+    }
+}
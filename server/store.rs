@@ -0,0 +1,387 @@
+use chrono::{DateTime, Utc};
+use rand::{distributions::Alphanumeric, thread_rng, Rng};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::json;
+use std::time::SystemTime;
+use unic_langid::LanguageIdentifier;
+
+use crate::errors::Error;
+use crate::migrations;
+use crate::playground::{
+    is_valid_resource_name, localized_filename, Playground, Resource, Translation, MESSAGES_FILE,
+};
+use crate::setup::Setup;
+use crate::variables::Variables;
+
+mod fs;
+mod gist;
+mod memory;
+mod sqlite;
+pub use self::fs::FsStore;
+pub use self::gist::GistStore;
+pub use self::memory::MemoryStore;
+pub use self::sqlite::SqliteStore;
+
+const ID_LENGTH: usize = 16;
+
+/// A place where playgrounds are kept.
+///
+/// Handlers only talk to the store through this trait, so the backend can be
+/// chosen at startup.
+pub trait PlaygroundStore: Send + Sync {
+    fn get(&self, id: &str) -> Result<Playground, Error>;
+    fn create(&self, playground: Playground) -> Result<Playground, Error>;
+    fn update(&self, id: &str, playground: Playground) -> Result<Playground, Error>;
+    fn delete(&self, id: &str) -> Result<(), Error>;
+
+    /// List the saved revisions of a playground, newest first.
+    fn revisions(&self, id: &str) -> Result<Vec<Revision>, Error>;
+
+    /// Get a playground as it was saved at the given revision.
+    fn revision(&self, id: &str, rev: &str) -> Result<Playground, Error>;
+
+    /// Copy a playground under a new id, recording the original's id as
+    /// `parent` in the copy's setup. The copy's FTL is canonicalized, like
+    /// that of any new playground.
+    ///
+    /// GitHub doesn't let an account fork its own gists, so the Gist store
+    /// relies on this too.
+    fn fork(&self, id: &str) -> Result<Playground, Error> {
+        let mut playground = self.get(id)?;
+        playground.setup.parent = Some(id.to_string());
+        playground.canonicalize();
+        self.create(playground)
+    }
+}
+
+/// A saved state of a playground.
+#[derive(Debug, Serialize)]
+pub struct Revision {
+    pub id: String,
+    /// When the revision was saved, as an RFC 3339 timestamp.
+    pub created_at: String,
+}
+
+/// Format a point in time the way GitHub formats gist timestamps.
+pub fn format_time(time: SystemTime) -> String {
+    DateTime::<Utc>::from(time)
+        .format("%Y-%m-%dT%H:%M:%SZ")
+        .to_string()
+}
+
+/// Generate a random id for backends which don't assign their own.
+pub fn generate_id() -> String {
+    thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(ID_LENGTH)
+        .collect()
+}
+
+/// Check that an id could have been produced by `generate_id`.
+pub fn is_valid_id(id: &str) -> bool {
+    !id.is_empty() && id.chars().all(|c| c.is_ascii_alphanumeric())
+}
+
+fn try_serialize_json(value: &impl Serialize) -> Result<String, Error> {
+    serde_json::ser::to_string_pretty(value).or(Err(Error::Serializing))
+}
+
+/// How a translation is recorded in a stored setup.
+#[derive(Debug, Serialize, Deserialize)]
+struct StoredTranslation {
+    locale: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    resources: Vec<String>,
+}
+
+fn resource_names(resources: &[Resource]) -> Vec<String> {
+    resources
+        .iter()
+        .map(|resource| resource.name.clone())
+        .collect()
+}
+
+/// The setup to store for a playground, recording its schema version, and
+/// its resources and translations for stores which keep them as separate
+/// files. Whatever the client sent under those keys is dropped, since the
+/// names in them are read back as files.
+fn stored_setup(playground: &Playground) -> serde_json::Value {
+    let mut setup = migrations::with_version(&json!(playground.setup));
+    if let Some(setup) = setup.as_object_mut() {
+        setup.remove("resources");
+        setup.remove("translations");
+    }
+    if !playground.resources.is_empty() {
+        setup["resources"] = json!(resource_names(&playground.resources));
+    }
+    if !playground.translations.is_empty() {
+        let translations: Vec<StoredTranslation> = playground
+            .translations
+            .iter()
+            .map(|translation| StoredTranslation {
+                locale: translation.locale.clone(),
+                resources: resource_names(&translation.resources),
+            })
+            .collect();
+        setup["translations"] = json!(translations);
+    }
+    setup
+}
+
+/// Take a value recorded by `stored_setup` back out of a stored setup.
+fn take_from_setup<T: DeserializeOwned + Default>(setup: &mut serde_json::Value, key: &str) -> T {
+    setup
+        .as_object_mut()
+        .and_then(|setup| setup.remove(key))
+        .and_then(|value| serde_json::from_value(value).ok())
+        .unwrap_or_default()
+}
+
+/// Read resources by name, refusing names which aren't plain `.ftl`
+/// filenames.
+fn read_named(
+    names: Vec<String>,
+    read: impl Fn(&str) -> Result<String, Error>,
+) -> Result<Vec<Resource>, Error> {
+    names
+        .into_iter()
+        .map(|name| {
+            if !is_valid_resource_name(&name) {
+                return Err(Error::Deserializing);
+            }
+            let content = read(&name)?;
+            Ok(Resource { name, content })
+        })
+        .collect()
+}
+
+/// Take the schema version out of a stored setup and bring the setup and
+/// variables up to date.
+fn upgrade(setup: &mut serde_json::Value, variables: &mut serde_json::Value) {
+    let version = migrations::take_version(setup);
+    migrations::migrate(version, setup, variables);
+}
+
+/// Turn an upgraded stored setup into a `Setup`, once `read_ftl_files` has
+/// taken out what the store recorded in it.
+fn load_setup(setup: serde_json::Value) -> Result<Setup, Error> {
+    serde_json::from_value(setup).or(Err(Error::Deserializing))
+}
+
+/// Turn upgraded stored variables into `Variables`.
+fn load_variables(variables: serde_json::Value) -> Result<Variables, Error> {
+    serde_json::from_value(variables).or(Err(Error::Deserializing))
+}
+
+/// Read the resources and translations recorded in a stored setup, using
+/// `read` to get the content of a file by its name.
+fn read_ftl_files(
+    setup: &mut serde_json::Value,
+    read: impl Fn(&str) -> Result<String, Error>,
+) -> Result<(Vec<Resource>, Vec<Translation>), Error> {
+    let resources = read_named(take_from_setup(setup, "resources"), &read)?;
+    let translations = take_from_setup::<Vec<StoredTranslation>>(setup, "translations")
+        .into_iter()
+        .map(|stored| {
+            let locale = stored.locale;
+            if locale.parse::<LanguageIdentifier>().is_err() {
+                return Err(Error::Deserializing);
+            }
+            Ok(Translation {
+                messages: read(&localized_filename(MESSAGES_FILE, &locale))?,
+                resources: read_named(stored.resources, |name| {
+                    read(&localized_filename(name, &locale))
+                })?,
+                locale,
+            })
+        })
+        .collect::<Result<_, Error>>()?;
+    Ok((resources, translations))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn playground() -> Playground {
+        Playground {
+            id: None,
+            filename: None,
+            messages: "hello = Hello\n".to_string(),
+            resources: Vec::new(),
+            translations: Vec::new(),
+            variables: Variables::default(),
+            setup: Setup::default(),
+        }
+    }
+
+    fn playground_with_files() -> Playground {
+        let mut playground = playground();
+        playground.resources.push(Resource {
+            name: "brand.ftl".to_string(),
+            content: "-brand = Firefox\n".to_string(),
+        });
+        playground.translations.push(Translation {
+            locale: "pl".to_string(),
+            messages: "hello = Cześć\n".to_string(),
+            resources: Vec::new(),
+        });
+        playground
+    }
+
+    fn is_not_found<T>(result: Result<T, Error>) -> bool {
+        matches!(result, Err(Error::NotFound))
+    }
+
+    /// Take a playground through its whole life in `store`.
+    fn round_trip(store: &dyn PlaygroundStore) {
+        let created = store.create(playground_with_files()).unwrap();
+        let id = created.id.clone().unwrap();
+        assert_eq!(created.messages, "hello = Hello\n");
+        assert_eq!(created.resources.len(), 1);
+        assert_eq!(created.translations.len(), 1);
+
+        let fetched = store.get(&id).unwrap();
+        assert_eq!(fetched.messages, "hello = Hello\n");
+        assert_eq!(fetched.resources[0].name, "brand.ftl");
+        assert_eq!(fetched.resources[0].content, "-brand = Firefox\n");
+        assert_eq!(fetched.translations[0].locale, "pl");
+        assert_eq!(fetched.translations[0].messages, "hello = Cześć\n");
+
+        let mut changed = playground_with_files();
+        changed.messages = "hello = Hi\n".to_string();
+        let updated = store.update(&id, changed).unwrap();
+        assert_eq!(updated.id.as_deref(), Some(id.as_str()));
+        assert_eq!(store.get(&id).unwrap().messages, "hello = Hi\n");
+
+        let revisions = store.revisions(&id).unwrap();
+        assert_eq!(revisions.len(), 2);
+        let oldest = store.revision(&id, &revisions[1].id).unwrap();
+        assert_eq!(oldest.messages, "hello = Hello\n");
+        let newest = store.revision(&id, &revisions[0].id).unwrap();
+        assert_eq!(newest.messages, "hello = Hi\n");
+        assert!(matches!(
+            store.revision(&id, "0"),
+            Err(Error::RevisionNotFound)
+        ));
+
+        store.delete(&id).unwrap();
+        assert!(is_not_found(store.get(&id)));
+        assert!(is_not_found(store.revisions(&id)));
+        assert!(is_not_found(store.delete(&id)));
+        assert!(is_not_found(store.update(&id, playground())));
+    }
+
+    #[test]
+    fn fork_canonicalizes_the_copy() {
+        let store = MemoryStore::new();
+        let mut original = playground_with_files();
+        original.messages = "hello =   Hello\n".to_string();
+        original.translations[0].messages = "hello =\n    Cześć\n".to_string();
+        let id = store.create(original).unwrap().id.unwrap();
+        let fork = store.fork(&id).unwrap();
+        assert_eq!(fork.setup.parent.as_deref(), Some(id.as_str()));
+        assert_eq!(fork.messages, "hello = Hello\n");
+        assert_eq!(fork.translations[0].messages, "hello = Cześć\n");
+        assert_eq!(store.get(&id).unwrap().messages, "hello =   Hello\n");
+    }
+
+    #[test]
+    fn memory_store_round_trip() {
+        round_trip(&MemoryStore::new());
+    }
+
+    #[test]
+    fn fs_store_round_trip() {
+        let dir = std::env::temp_dir().join(format!("fluent-play-test-{}", generate_id()));
+        let result = std::panic::catch_unwind(|| round_trip(&FsStore::new(&dir).unwrap()));
+        std::fs::remove_dir_all(&dir).unwrap();
+        result.unwrap();
+    }
+
+    #[test]
+    fn fs_store_keeps_every_concurrent_update() {
+        let dir = std::env::temp_dir().join(format!("fluent-play-test-{}", generate_id()));
+        let store = std::sync::Arc::new(FsStore::new(&dir).unwrap());
+        let id = store.create(playground()).unwrap().id.unwrap();
+        let threads: Vec<_> = (0..8)
+            .map(|_| {
+                let store = store.clone();
+                let id = id.clone();
+                std::thread::spawn(move || store.update(&id, playground()).is_ok())
+            })
+            .collect();
+        let updated = threads.into_iter().all(|thread| thread.join().unwrap());
+        let revisions = store.revisions(&id);
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert!(updated);
+        assert_eq!(revisions.unwrap().len(), 9);
+    }
+
+    #[test]
+    fn fs_store_opens_setup_saved_before_validation() {
+        let root = std::env::temp_dir().join(format!("fluent-play-test-{}", generate_id()));
+        let store = FsStore::new(&root).unwrap();
+        let dir = root.join("legacy");
+        std::fs::create_dir(&dir).unwrap();
+        std::fs::write(dir.join("playground.ftl"), "hello = Hello\n").unwrap();
+        std::fs::write(dir.join("playground.json"), "{}").unwrap();
+        let setup = r#"{"schema_version": 1, "locale": "en US", "dir": "RTL"}"#;
+        std::fs::write(dir.join("setup.json"), setup).unwrap();
+        let result = store.get("legacy");
+        std::fs::remove_dir_all(&root).unwrap();
+        let setup = result.unwrap().setup;
+        assert_eq!(setup.locale, "en-US");
+        assert_eq!(setup.dir, crate::setup::Dir::Rtl);
+    }
+
+    #[test]
+    fn sqlite_store_round_trip() {
+        round_trip(&SqliteStore::open(":memory:").unwrap());
+    }
+
+    #[test]
+    fn stored_setup_drops_file_names_from_the_client() {
+        let mut playground = playground();
+        playground
+            .setup
+            .extra
+            .insert("resources".to_string(), json!(["../../../etc/passwd"]));
+        playground.setup.extra.insert(
+            "translations".to_string(),
+            json!([{ "locale": "../../etc", "resources": [] }]),
+        );
+        let setup = stored_setup(&playground);
+        assert!(setup.get("resources").is_none());
+        assert!(setup.get("translations").is_none());
+        let setup = stored_setup(&playground_with_files());
+        assert_eq!(setup["resources"], json!(["brand.ftl"]));
+        assert_eq!(setup["translations"], json!([{ "locale": "pl" }]));
+    }
+
+    #[test]
+    fn read_ftl_files_refuses_paths() {
+        let read = |name: &str| -> Result<String, Error> { panic!("Read {}", name) };
+        let mut setup = json!({ "resources": ["../../../etc/passwd.ftl"] });
+        assert!(read_ftl_files(&mut setup, read).is_err());
+        let mut setup = json!({ "resources": ["/etc/passwd.ftl"] });
+        assert!(read_ftl_files(&mut setup, read).is_err());
+        let mut setup = json!({ "translations": [{ "locale": "../../etc" }] });
+        assert!(read_ftl_files(&mut setup, read).is_err());
+    }
+
+    #[test]
+    fn read_ftl_files_reads_resources_and_translations() {
+        let read = |name: &str| Ok(format!("# {}\n", name));
+        let mut setup = json!({
+            "locale": "en-US",
+            "resources": ["brand.ftl"],
+            "translations": [{ "locale": "pl", "resources": ["brand.ftl"] }],
+        });
+        let (resources, translations) = read_ftl_files(&mut setup, read).unwrap();
+        assert_eq!(resources[0].content, "# brand.ftl\n");
+        assert_eq!(translations[0].messages, "# playground.pl.ftl\n");
+        assert_eq!(translations[0].resources[0].content, "# brand.pl.ftl\n");
+        assert_eq!(setup, json!({ "locale": "en-US" }));
+    }
+}
@@ -0,0 +1,177 @@
+use std::cmp::Reverse;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use crate::errors::Error;
+use crate::playground::{Playground, MESSAGES_FILE};
+use crate::store::{
+    format_time, generate_id, is_valid_id, load_setup, load_variables, read_ftl_files,
+    stored_setup, try_serialize_json, upgrade, PlaygroundStore, Revision,
+};
+
+/// Keeps each playground in its own directory under `root`, using the same
+/// three files as a playground gist. Every save is also copied to a numbered
+/// directory under `revisions`.
+pub struct FsStore {
+    root: PathBuf,
+}
+
+impl FsStore {
+    pub fn new(root: impl Into<PathBuf>) -> io::Result<Self> {
+        let root = root.into();
+        fs::create_dir_all(&root)?;
+        Ok(FsStore { root })
+    }
+
+    fn dir(&self, id: &str) -> Result<PathBuf, Error> {
+        if !is_valid_id(id) {
+            return Err(Error::NotFound);
+        }
+        let dir = self.root.join(id);
+        if dir.is_dir() {
+            Ok(dir)
+        } else {
+            Err(Error::NotFound)
+        }
+    }
+}
+
+fn read_file(dir: &Path, name: &str) -> Result<String, Error> {
+    match fs::read_to_string(dir.join(name)) {
+        Ok(content) => Ok(content),
+        Err(ref err) if err.kind() == io::ErrorKind::NotFound => {
+            Err(Error::MissingFile(name.to_string()))
+        }
+        Err(_) => Err(Error::Fetching),
+    }
+}
+
+fn read_json(dir: &Path, name: &str) -> Result<serde_json::Value, Error> {
+    serde_json::from_str(&read_file(dir, name)?).or(Err(Error::Deserializing))
+}
+
+/// Write through a temporary file so that readers never see a partial file.
+/// Each write gets its own temporary file, so that concurrent saves don't
+/// rename each other's.
+fn write_file(dir: &Path, name: &str, content: &str) -> io::Result<()> {
+    let tmp = dir.join(format!(".{}.{}.tmp", name, generate_id()));
+    fs::write(&tmp, content)?;
+    fs::rename(&tmp, dir.join(name))
+}
+
+fn write_files(dir: &Path, files: &[(String, &str)]) -> io::Result<()> {
+    for (name, content) in files {
+        write_file(dir, name, content)?;
+    }
+    Ok(())
+}
+
+/// List the revisions of the playground in `dir`, newest first.
+fn list_revisions(dir: &Path) -> io::Result<Vec<(u32, SystemTime)>> {
+    let entries = match fs::read_dir(dir.join("revisions")) {
+        Ok(entries) => entries,
+        Err(ref err) if err.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(err) => return Err(err),
+    };
+    let mut revisions = Vec::new();
+    for entry in entries {
+        let entry = entry?;
+        if let Ok(rev) = entry.file_name().to_string_lossy().parse::<u32>() {
+            revisions.push((rev, entry.metadata()?.modified()?));
+        }
+    }
+    revisions.sort_by_key(|&(rev, _)| Reverse(rev));
+    Ok(revisions)
+}
+
+/// Create the directory for the next revision of the playground in `dir`.
+///
+/// Saves racing for the same number are told apart by `create_dir` failing
+/// for all but one of them; the others try the number after.
+fn create_revision(dir: &Path) -> io::Result<PathBuf> {
+    fs::create_dir_all(dir.join("revisions"))?;
+    loop {
+        let last = list_revisions(dir)?.first().map_or(0, |&(rev, _)| rev);
+        let revision = dir.join("revisions").join((last + 1).to_string());
+        match fs::create_dir(&revision) {
+            Ok(()) => return Ok(revision),
+            Err(ref err) if err.kind() == io::ErrorKind::AlreadyExists => continue,
+            Err(err) => return Err(err),
+        }
+    }
+}
+
+fn write_playground(dir: &Path, playground: &Playground, err: Error) -> Result<(), Error> {
+    let variables = try_serialize_json(&playground.variables)?;
+    let setup = try_serialize_json(&stored_setup(playground))?;
+    let mut files = playground.files();
+    files.push(("playground.json".to_string(), &variables));
+    files.push(("setup.json".to_string(), &setup));
+    create_revision(dir)
+        .and_then(|revision| write_files(&revision, &files))
+        .and_then(|_| write_files(dir, &files))
+        .or(Err(err))
+}
+
+fn read_playground(dir: &Path, id: &str) -> Result<Playground, Error> {
+    let mut setup = read_json(dir, "setup.json")?;
+    let mut variables = read_json(dir, "playground.json")?;
+    upgrade(&mut setup, &mut variables);
+    let (resources, translations) = read_ftl_files(&mut setup, |name| read_file(dir, name))?;
+    Ok(Playground {
+        id: Some(id.to_string()),
+        filename: None,
+        messages: read_file(dir, MESSAGES_FILE)?,
+        resources,
+        translations,
+        variables: load_variables(variables)?,
+        setup: load_setup(setup)?,
+    })
+}
+
+impl PlaygroundStore for FsStore {
+    fn get(&self, id: &str) -> Result<Playground, Error> {
+        read_playground(&self.dir(id)?, id)
+    }
+
+    fn create(&self, playground: Playground) -> Result<Playground, Error> {
+        let id = generate_id();
+        let dir = self.root.join(&id);
+        fs::create_dir(&dir).or(Err(Error::Creating))?;
+        write_playground(&dir, &playground, Error::Creating)?;
+        read_playground(&dir, &id)
+    }
+
+    fn update(&self, id: &str, playground: Playground) -> Result<Playground, Error> {
+        let dir = self.dir(id)?;
+        write_playground(&dir, &playground, Error::Updating)?;
+        read_playground(&dir, id)
+    }
+
+    fn delete(&self, id: &str) -> Result<(), Error> {
+        fs::remove_dir_all(self.dir(id)?).or(Err(Error::Deleting))
+    }
+
+    fn revisions(&self, id: &str) -> Result<Vec<Revision>, Error> {
+        let revisions = list_revisions(&self.dir(id)?).or(Err(Error::Fetching))?;
+        Ok(revisions
+            .into_iter()
+            .map(|(rev, time)| Revision {
+                id: rev.to_string(),
+                created_at: format_time(time),
+            })
+            .collect())
+    }
+
+    fn revision(&self, id: &str, rev: &str) -> Result<Playground, Error> {
+        let dir = self.dir(id)?;
+        let rev: u32 = rev.parse().or(Err(Error::RevisionNotFound))?;
+        let revision = dir.join("revisions").join(rev.to_string());
+        if !revision.is_dir() {
+            return Err(Error::RevisionNotFound);
+        }
+        read_playground(&revision, id)
+    }
+}
@@ -0,0 +1,491 @@
+use futures::{Future, Stream};
+use hubcaps::{gists, Credentials, Github};
+use hyper::{self, header, StatusCode};
+use hyper_tls::HttpsConnector;
+use serde::Deserialize;
+use serde_json::json;
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use tokio::runtime::Runtime;
+use unic_langid::LanguageIdentifier;
+
+use crate::errors::Error;
+use crate::playground::{Playground, Resource, MESSAGES_FILE};
+use crate::store::{
+    load_setup, load_variables, read_ftl_files, stored_setup, try_serialize_json, upgrade,
+    PlaygroundStore, Revision,
+};
+
+const USER_AGENT: &str = concat!(env!("CARGO_PKG_NAME"), "/", env!("CARGO_PKG_VERSION"));
+const API_URL: &str = "https://api.github.com";
+const RAW_URL_PREFIX: &str = "https://gist.githubusercontent.com/";
+/// The most commits GitHub lists on one page.
+const COMMITS_PER_PAGE: usize = 100;
+
+type Connector = HttpsConnector<hyper::client::HttpConnector>;
+
+pub struct GistStore {
+    gists: gists::Gists<Connector>,
+    http: hyper::Client<Connector>,
+    token: String,
+    max_file_size: u64,
+}
+
+impl GistStore {
+    pub fn new(token: String, max_file_size: u64) -> Self {
+        let github = Github::new(USER_AGENT, Credentials::Token(token.clone()));
+        let connector = HttpsConnector::new(4).expect("Unable to create TLS connector");
+        GistStore {
+            gists: github.gists(),
+            http: hyper::Client::builder().build(connector),
+            token,
+            max_file_size,
+        }
+    }
+
+    /// Turn a gist into a playground, first fetching the full content of any
+    /// playground files which GitHub truncated.
+    fn load(&self, gist: gists::Gist) -> Result<Playground, Error> {
+        load_gist(gist, self.max_file_size, |url| self.fetch_raw(url))
+    }
+
+    /// Fetch a resource which hubcaps doesn't know about.
+    fn fetch(&self, url: &str) -> Result<hyper::Chunk, Error> {
+        let request = hyper::Request::get(url)
+            .header(header::USER_AGENT, USER_AGENT)
+            .header(header::AUTHORIZATION, format!("token {}", self.token))
+            .body(hyper::Body::empty())
+            .or(Err(Error::Fetching))?;
+        self.send(request)
+    }
+
+    /// Fetch the full content of a truncated file. Raw files are public, so
+    /// the API token isn't sent along.
+    fn fetch_raw(&self, url: &str) -> Result<hyper::Chunk, Error> {
+        let request = hyper::Request::get(url)
+            .header(header::USER_AGENT, USER_AGENT)
+            .body(hyper::Body::empty())
+            .or(Err(Error::Fetching))?;
+        self.send(request)
+    }
+
+    fn send(&self, request: hyper::Request<hyper::Body>) -> Result<hyper::Chunk, Error> {
+        let response = self.http.request(request).and_then(|response| {
+            let status = response.status();
+            response
+                .into_body()
+                .concat2()
+                .map(move |body| (status, body))
+        });
+        match run(response)? {
+            Ok((StatusCode::NOT_FOUND, _)) => Err(Error::NotFound),
+            Ok((status, body)) if status.is_success() => Ok(body),
+            _ => Err(Error::Fetching),
+        }
+    }
+}
+
+fn load_gist<F, B>(mut gist: gists::Gist, max_file_size: u64, fetch: F) -> Result<Playground, Error>
+where
+    F: Fn(&str) -> Result<B, Error>,
+    B: AsRef<[u8]>,
+{
+    for (name, file) in gist.files.iter_mut() {
+        if !is_playground_file(name) {
+            continue;
+        }
+        if file.size > max_file_size {
+            return Err(Error::FileTooLarge(name.clone()));
+        }
+        if file.truncated == Some(true) {
+            if !is_raw_url(&file.raw_url) {
+                return Err(Error::Fetching);
+            }
+            let body = fetch(&file.raw_url)?;
+            let content =
+                String::from_utf8(body.as_ref().to_vec()).or(Err(Error::Deserializing))?;
+            file.content = Some(content);
+        }
+    }
+    Playground::try_from(gist)
+}
+
+/// An entry in the list returned by GitHub's gist commits API.
+#[derive(Debug, Deserialize)]
+struct GistCommit {
+    version: String,
+    committed_at: String,
+}
+
+fn run<F>(future: F) -> Result<Result<F::Item, F::Error>, Error>
+where
+    F: Future + Send + 'static,
+    F::Item: Send + 'static,
+    F::Error: Send + 'static,
+{
+    let mut rt = Runtime::new().or(Err(Error::Runtime))?;
+    Ok(rt.block_on(future))
+}
+
+fn block_on<T: Send + 'static>(future: hubcaps::Future<T>, err: Error) -> Result<T, Error> {
+    match run(future)? {
+        Ok(value) => Ok(value),
+        Err(hubcaps::errors::Error(hubcaps::errors::ErrorKind::Fault { code, .. }, _))
+            if code == 404 =>
+        {
+            Err(Error::NotFound)
+        }
+        Err(_) => Err(err),
+    }
+}
+
+impl PlaygroundStore for GistStore {
+    fn get(&self, id: &str) -> Result<Playground, Error> {
+        let gist = block_on(self.gists.get(id), Error::Fetching)?;
+        self.load(gist)
+    }
+
+    fn create(&self, playground: Playground) -> Result<Playground, Error> {
+        let options = gists::GistOptions::try_from(playground)?;
+        let gist = block_on(self.gists.create(&options), Error::Creating)?;
+        self.load(gist)
+    }
+
+    fn update(&self, id: &str, playground: Playground) -> Result<Playground, Error> {
+        let options = gists::GistOptions::try_from(playground)?;
+        let gist = block_on(self.gists.edit(id, &options), Error::Updating)?;
+        self.load(gist)
+    }
+
+    fn delete(&self, id: &str) -> Result<(), Error> {
+        block_on(self.gists.delete(id), Error::Deleting)
+    }
+
+    fn revisions(&self, id: &str) -> Result<Vec<Revision>, Error> {
+        let mut revisions = Vec::new();
+        for page in 1.. {
+            let body = self.fetch(&format!(
+                "{}/gists/{}/commits?per_page={}&page={}",
+                API_URL, id, COMMITS_PER_PAGE, page
+            ))?;
+            let commits: Vec<GistCommit> =
+                serde_json::from_slice(&body).or(Err(Error::Deserializing))?;
+            let is_last_page = commits.len() < COMMITS_PER_PAGE;
+            revisions.extend(commits.into_iter().map(|commit| Revision {
+                id: commit.version,
+                created_at: commit.committed_at,
+            }));
+            if is_last_page {
+                break;
+            }
+        }
+        Ok(revisions)
+    }
+
+    fn revision(&self, id: &str, rev: &str) -> Result<Playground, Error> {
+        let gist = match block_on(self.gists.getrev(id, rev), Error::Fetching) {
+            Err(Error::NotFound) => return Err(Error::RevisionNotFound),
+            result => result?,
+        };
+        self.load(gist)
+    }
+}
+
+fn try_file_content<'gist>(gist: &'gist gists::Gist, name: &str) -> Result<&'gist String, Error> {
+    gist.files
+        .get(name)
+        .ok_or_else(|| Error::MissingFile(name.to_string()))?
+        .content
+        .as_ref()
+        .ok_or_else(|| Error::EmptyFile(name.to_string()))
+}
+
+/// Check that a file's raw URL points at GitHub's host for gist content,
+/// before following it.
+fn is_raw_url(url: &str) -> bool {
+    url.starts_with(RAW_URL_PREFIX)
+}
+
+fn is_playground_file(name: &str) -> bool {
+    name.ends_with(".ftl") || name == "playground.json" || name == "setup.json"
+}
+
+/// Deserialize a JSON file from the gist, or return `None` if the gist
+/// doesn't have it.
+fn try_deserialize_json(
+    gist: &gists::Gist,
+    name: &str,
+) -> Result<Option<serde_json::value::Value>, Error> {
+    match try_file_content(gist, name) {
+        Ok(content) => serde_json::from_str(content)
+            .map(Some)
+            .or(Err(Error::Deserializing)),
+        Err(Error::MissingFile(_)) => Ok(None),
+        Err(err) => Err(err),
+    }
+}
+
+/// Pick the file holding the messages: `playground.ftl` if the gist was
+/// created by the playground, or else the first `.ftl` file by name.
+fn messages_filename(gist: &gists::Gist) -> Result<&str, Error> {
+    if gist.files.contains_key(MESSAGES_FILE) {
+        return Ok(MESSAGES_FILE);
+    }
+    gist.files
+        .keys()
+        .filter(|name| name.ends_with(".ftl"))
+        .min()
+        .map(String::as_str)
+        .ok_or_else(|| Error::MissingFile("*.ftl".to_string()))
+}
+
+/// Names which are common for FTL files but also happen to parse as language
+/// tags.
+const NOT_LOCALES: &[&str] = &["app", "default", "main", "messages", "strings", "und"];
+
+/// Guess the locale of a gist which doesn't have `setup.json` from the name of
+/// its FTL file, e.g. `pl.ftl`, `mai.ftl` or `en-US.ftl`.
+///
+/// Language subtags of five to eight letters are allowed by BCP 47 but none
+/// have been registered, so names like `welcome_screen.ftl` aren't taken for
+/// locales.
+fn infer_locale(filename: &str) -> String {
+    let stem = filename.trim_end_matches(".ftl");
+    if NOT_LOCALES.contains(&stem.to_lowercase().as_str()) {
+        return "en-US".to_string();
+    }
+    match stem.parse::<LanguageIdentifier>() {
+        Ok(langid) if langid.language.as_str().len() <= 3 => langid.to_string(),
+        _ => "en-US".to_string(),
+    }
+}
+
+impl TryFrom<gists::Gist> for Playground {
+    type Error = Error;
+    fn try_from(gist: gists::Gist) -> Result<Self, Self::Error> {
+        let filename = messages_filename(&gist)?;
+        let read = |name: &str| Ok(try_file_content(&gist, name)?.clone());
+        let (mut setup, resources, translations) = match try_deserialize_json(&gist, "setup.json")?
+        {
+            Some(mut setup) => {
+                let (resources, translations) = read_ftl_files(&mut setup, read)?;
+                (setup, resources, translations)
+            }
+            None => {
+                // Without a setup, any other FTL files are resources.
+                let mut names: Vec<&String> = gist
+                    .files
+                    .keys()
+                    .filter(|name| name.ends_with(".ftl") && name.as_str() != filename)
+                    .collect();
+                names.sort();
+                let resources = names
+                    .into_iter()
+                    .map(|name| {
+                        Ok(Resource {
+                            name: name.clone(),
+                            content: read(name)?,
+                        })
+                    })
+                    .collect::<Result<_, Error>>()?;
+                let setup = json!({ "locale": infer_locale(filename) });
+                (setup, resources, Vec::new())
+            }
+        };
+        let mut variables =
+            try_deserialize_json(&gist, "playground.json")?.unwrap_or_else(|| json!({}));
+        upgrade(&mut setup, &mut variables);
+        Ok(Playground {
+            id: Some(gist.id.clone()),
+            filename: Some(filename.to_string()),
+            messages: read(filename)?,
+            resources,
+            translations,
+            variables: load_variables(variables)?,
+            setup: load_setup(setup)?,
+        })
+    }
+}
+
+impl TryFrom<Playground> for gists::GistOptions {
+    type Error = Error;
+    fn try_from(playground: Playground) -> Result<Self, Self::Error> {
+        let setup = stored_setup(&playground);
+        let mut files = HashMap::new();
+        for (name, content) in playground.files() {
+            files.insert(
+                name,
+                gists::Content {
+                    filename: None,
+                    content: content.to_string(),
+                },
+            );
+        }
+        files.insert(
+            "playground.json".to_string(),
+            gists::Content {
+                filename: None,
+                content: try_serialize_json(&playground.variables)?,
+            },
+        );
+        files.insert(
+            "setup.json".to_string(),
+            gists::Content {
+                filename: None,
+                content: try_serialize_json(&setup)?,
+            },
+        );
+        Ok(gists::GistOptions {
+            description: Some("A Fluent Playground snippet".to_string()),
+            public: Some(true),
+            files,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::setup::Dir;
+    use crate::variables::Variable;
+
+    fn plain_gist() -> gists::Gist {
+        serde_json::from_str(include_str!("../fixtures/gist/plain.json")).unwrap()
+    }
+
+    #[test]
+    fn loads_gist_saved_before_versioning() {
+        let gist: gists::Gist =
+            serde_json::from_str(include_str!("../fixtures/gist/legacy.json")).unwrap();
+        let playground = Playground::try_from(gist).unwrap();
+        assert!(playground.messages.starts_with("# Try editing"));
+        assert!(playground.resources.is_empty());
+        assert_eq!(playground.setup.visible, ["messages", "config", "output"]);
+        assert_eq!(playground.setup.locale, "pl");
+        assert_eq!(playground.setup.dir, Dir::Ltr);
+        assert_eq!(
+            playground.variables.0["photoCount"],
+            Variable::Number {
+                value: 22.0,
+                options: Default::default(),
+            }
+        );
+    }
+
+    #[test]
+    fn loads_gist_without_setup_or_variables() {
+        let playground = Playground::try_from(plain_gist()).unwrap();
+        assert_eq!(playground.id.as_deref(), Some("5b2f9e0c1d7a4e8f6a3b"));
+        assert_eq!(playground.filename.as_deref(), Some("pl.ftl"));
+        assert!(playground.messages.starts_with("welcome = Witaj"));
+        assert!(playground.variables.0.is_empty());
+        assert_eq!(playground.setup.visible, ["messages", "output"]);
+        assert_eq!(playground.setup.locale, "pl");
+        assert_eq!(playground.setup.dir, Dir::Ltr);
+    }
+
+    #[test]
+    fn loads_the_first_ftl_file_by_name() {
+        let mut gist = plain_gist();
+        let messages = gist.files.remove("pl.ftl").unwrap();
+        gist.files.insert("strings.ftl".to_string(), messages);
+        let messages = plain_gist().files.remove("pl.ftl").unwrap();
+        gist.files.insert("main.ftl".to_string(), messages);
+        let playground = Playground::try_from(gist).unwrap();
+        assert_eq!(playground.filename.as_deref(), Some("main.ftl"));
+        assert_eq!(playground.setup.locale, "en-US");
+    }
+
+    #[test]
+    fn refuses_gist_without_ftl_files() {
+        let mut gist = plain_gist();
+        gist.files.remove("pl.ftl");
+        match Playground::try_from(gist) {
+            Err(Error::MissingFile(name)) => assert_eq!(name, "*.ftl"),
+            other => panic!("Expected MissingFile, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn infers_locale_from_filename() {
+        assert_eq!(infer_locale("pl.ftl"), "pl");
+        assert_eq!(infer_locale("en-US.ftl"), "en-US");
+        assert_eq!(infer_locale("mai.ftl"), "mai");
+        assert_eq!(infer_locale("fil.ftl"), "fil");
+        assert_eq!(infer_locale("sr-Latn.ftl"), "sr-Latn");
+        assert_eq!(infer_locale("messages.ftl"), "en-US");
+        assert_eq!(infer_locale("Strings.ftl"), "en-US");
+        assert_eq!(infer_locale("playground.ftl"), "en-US");
+        assert_eq!(infer_locale("welcome_screen.ftl"), "en-US");
+    }
+
+    fn fixture() -> gists::Gist {
+        serde_json::from_str(include_str!("../fixtures/gist/gist.json")).unwrap()
+    }
+
+    #[test]
+    fn loads_gist_fetching_truncated_files() {
+        let playground = load_gist(fixture(), 1024, |url| {
+            assert_eq!(url, "https://gist.githubusercontent.com/raw/brand.ftl");
+            Ok("-brand = Firefox\n")
+        })
+        .unwrap();
+        assert_eq!(playground.id.as_deref(), Some("aa5a315d61ae9438b18d"));
+        assert!(playground.messages.starts_with("hello = Hello"));
+        assert_eq!(playground.resources.len(), 1);
+        assert_eq!(playground.resources[0].name, "brand.ftl");
+        assert_eq!(playground.resources[0].content, "-brand = Firefox\n");
+        assert_eq!(playground.setup.locale, "en-US");
+        assert_eq!(playground.variables.0.len(), 2);
+    }
+
+    #[test]
+    fn loads_gist_without_truncated_files() {
+        let mut gist = fixture();
+        let brand = gist.files.get_mut("brand.ftl").unwrap();
+        brand.truncated = Some(false);
+        brand.content = Some("-brand = Firefox\n".to_string());
+        let playground = load_gist(gist, 1024, |url| -> Result<&str, Error> {
+            panic!("Fetched {} although nothing was truncated", url)
+        })
+        .unwrap();
+        assert_eq!(playground.resources[0].content, "-brand = Firefox\n");
+    }
+
+    #[test]
+    fn refuses_files_larger_than_the_limit() {
+        let result = load_gist(fixture(), 80, |_| Ok(""));
+        match result {
+            Err(Error::FileTooLarge(name)) => assert_eq!(name, "setup.json"),
+            other => panic!("Expected FileTooLarge, got {:?}", other.map(|_| ())),
+        }
+    }
+
+    #[test]
+    fn refuses_truncated_files_outside_github() {
+        let mut gist = fixture();
+        let brand = gist.files.get_mut("brand.ftl").unwrap();
+        brand.raw_url = "https://example.com/brand.ftl".to_string();
+        let result = load_gist(gist, 1024, |url| -> Result<&str, Error> {
+            panic!("Fetched {} from outside GitHub", url)
+        });
+        assert!(matches!(result, Err(Error::Fetching)));
+    }
+
+    #[test]
+    fn fetches_only_raw_gist_urls() {
+        assert!(is_raw_url(
+            "https://gist.githubusercontent.com/user/aa5a315d/raw/abc/brand.ftl"
+        ));
+        assert!(!is_raw_url("https://example.com/brand.ftl"));
+        assert!(!is_raw_url(
+            "http://gist.githubusercontent.com/raw/brand.ftl"
+        ));
+        assert!(!is_raw_url(
+            "https://gist.githubusercontent.com.example.com/brand.ftl"
+        ));
+        assert!(!is_raw_url(
+            "https://gist.githubusercontent.com@example.com/brand.ftl"
+        ));
+    }
+}
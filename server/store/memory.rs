@@ -0,0 +1,89 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::SystemTime;
+
+use crate::errors::Error;
+use crate::playground::Playground;
+use crate::store::{format_time, generate_id, PlaygroundStore, Revision};
+
+/// Keeps playgrounds in memory, for running the server and its tests without
+/// the network or a disk. Playgrounds are kept serialized, so that callers
+/// can't change a stored playground through the values they are handed.
+#[derive(Default)]
+pub struct MemoryStore {
+    /// The saved revisions of each playground, oldest first.
+    playgrounds: Mutex<HashMap<String, Vec<(String, SystemTime)>>>,
+}
+
+impl MemoryStore {
+    pub fn new() -> Self {
+        MemoryStore::default()
+    }
+}
+
+fn save(playground: &Playground) -> Result<String, Error> {
+    serde_json::to_string(playground).or(Err(Error::Serializing))
+}
+
+fn load(id: &str, json: &str) -> Result<Playground, Error> {
+    let playground: Playground = serde_json::from_str(json).or(Err(Error::Deserializing))?;
+    Ok(Playground {
+        id: Some(id.to_string()),
+        ..playground
+    })
+}
+
+impl PlaygroundStore for MemoryStore {
+    fn get(&self, id: &str) -> Result<Playground, Error> {
+        let playgrounds = self.playgrounds.lock().or(Err(Error::Fetching))?;
+        match playgrounds.get(id).and_then(|revisions| revisions.last()) {
+            Some((json, _)) => load(id, json),
+            None => Err(Error::NotFound),
+        }
+    }
+
+    fn create(&self, playground: Playground) -> Result<Playground, Error> {
+        let json = save(&playground)?;
+        let id = generate_id();
+        let mut playgrounds = self.playgrounds.lock().or(Err(Error::Creating))?;
+        playgrounds.insert(id.clone(), vec![(json.clone(), SystemTime::now())]);
+        load(&id, &json)
+    }
+
+    fn update(&self, id: &str, playground: Playground) -> Result<Playground, Error> {
+        let json = save(&playground)?;
+        let mut playgrounds = self.playgrounds.lock().or(Err(Error::Updating))?;
+        let revisions = playgrounds.get_mut(id).ok_or(Error::NotFound)?;
+        revisions.push((json.clone(), SystemTime::now()));
+        load(id, &json)
+    }
+
+    fn delete(&self, id: &str) -> Result<(), Error> {
+        let mut playgrounds = self.playgrounds.lock().or(Err(Error::Deleting))?;
+        playgrounds.remove(id).map(|_| ()).ok_or(Error::NotFound)
+    }
+
+    fn revisions(&self, id: &str) -> Result<Vec<Revision>, Error> {
+        let playgrounds = self.playgrounds.lock().or(Err(Error::Fetching))?;
+        let revisions = playgrounds.get(id).ok_or(Error::NotFound)?;
+        Ok(revisions
+            .iter()
+            .enumerate()
+            .rev()
+            .map(|(index, (_, time))| Revision {
+                id: (index + 1).to_string(),
+                created_at: format_time(*time),
+            })
+            .collect())
+    }
+
+    fn revision(&self, id: &str, rev: &str) -> Result<Playground, Error> {
+        let playgrounds = self.playgrounds.lock().or(Err(Error::Fetching))?;
+        let revisions = playgrounds.get(id).ok_or(Error::NotFound)?;
+        let rev: usize = rev.parse().or(Err(Error::RevisionNotFound))?;
+        match rev.checked_sub(1).and_then(|index| revisions.get(index)) {
+            Some((json, _)) => load(id, json),
+            None => Err(Error::RevisionNotFound),
+        }
+    }
+}
@@ -0,0 +1,424 @@
+use rusqlite::{params, Connection, OptionalExtension, Row, NO_PARAMS};
+use sha2::{Digest, Sha256};
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::errors::Error;
+use crate::migrations::{self, SCHEMA_VERSION};
+use crate::playground::Playground;
+use crate::store::{
+    format_time, generate_id, is_valid_id, load_setup, load_variables, PlaygroundStore, Revision,
+};
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS playgrounds (
+        id TEXT PRIMARY KEY NOT NULL,
+        messages TEXT NOT NULL,
+        resources TEXT NOT NULL DEFAULT '[]',
+        translations TEXT NOT NULL DEFAULT '[]',
+        variables TEXT NOT NULL,
+        setup TEXT NOT NULL,
+        schema_version INTEGER NOT NULL DEFAULT 0,
+        locale TEXT,
+        created_at INTEGER NOT NULL,
+        updated_at INTEGER NOT NULL,
+        size INTEGER NOT NULL,
+        content_hash TEXT NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS playgrounds_created_at ON playgrounds (created_at);
+    CREATE INDEX IF NOT EXISTS playgrounds_locale ON playgrounds (locale);
+    CREATE INDEX IF NOT EXISTS playgrounds_content_hash ON playgrounds (content_hash);
+    CREATE TABLE IF NOT EXISTS revisions (
+        playground_id TEXT NOT NULL,
+        revision INTEGER NOT NULL,
+        messages TEXT NOT NULL,
+        resources TEXT NOT NULL DEFAULT '[]',
+        translations TEXT NOT NULL DEFAULT '[]',
+        variables TEXT NOT NULL,
+        setup TEXT NOT NULL,
+        schema_version INTEGER NOT NULL DEFAULT 0,
+        created_at INTEGER NOT NULL,
+        PRIMARY KEY (playground_id, revision)
+    );
+";
+
+/// Columns which were added after the tables were first created, so that
+/// older databases can be upgraded.
+const ADDED_COLUMNS: &[(&str, &str, &str)] = &[
+    ("playgrounds", "resources", "TEXT NOT NULL DEFAULT '[]'"),
+    ("revisions", "resources", "TEXT NOT NULL DEFAULT '[]'"),
+    ("playgrounds", "translations", "TEXT NOT NULL DEFAULT '[]'"),
+    ("revisions", "translations", "TEXT NOT NULL DEFAULT '[]'"),
+    (
+        "playgrounds",
+        "schema_version",
+        "INTEGER NOT NULL DEFAULT 0",
+    ),
+    ("revisions", "schema_version", "INTEGER NOT NULL DEFAULT 0"),
+];
+
+/// Keeps playgrounds in a single SQLite database, alongside metadata which
+/// can be used to query them. Every save is also added to `revisions`.
+///
+/// SQLite connections can't be shared between threads, so Iron's workers take
+/// turns using a single connection.
+pub struct SqliteStore {
+    conn: Mutex<Connection>,
+}
+
+impl SqliteStore {
+    pub fn open(path: impl AsRef<Path>) -> rusqlite::Result<Self> {
+        let conn = Connection::open(path)?;
+        conn.execute_batch(SCHEMA)?;
+        for (table, column, definition) in ADDED_COLUMNS {
+            add_column(&conn, table, column, definition)?;
+        }
+        Ok(SqliteStore {
+            conn: Mutex::new(conn),
+        })
+    }
+}
+
+/// Add a column to `table` unless it's already there.
+fn add_column(
+    conn: &Connection,
+    table: &str,
+    column: &str,
+    definition: &str,
+) -> rusqlite::Result<()> {
+    let mut stmt = conn.prepare(&format!("PRAGMA table_info({})", table))?;
+    let columns = stmt
+        .query_map(NO_PARAMS, |row| row.get::<_, String>(1))?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    if !columns.iter().any(|name| name == column) {
+        conn.execute_batch(&format!(
+            "ALTER TABLE {} ADD COLUMN {} {}",
+            table, column, definition
+        ))?;
+    }
+    Ok(())
+}
+
+/// The columns derived from a playground's content.
+struct Record {
+    messages: String,
+    resources: String,
+    translations: String,
+    variables: String,
+    setup: String,
+    locale: Option<String>,
+    size: i64,
+    content_hash: String,
+}
+
+impl Record {
+    fn try_from_playground(playground: &Playground) -> Result<Self, Error> {
+        let variables =
+            serde_json::ser::to_string(&playground.variables).or(Err(Error::Serializing))?;
+        let setup = serde_json::ser::to_string(&playground.setup).or(Err(Error::Serializing))?;
+        let resources =
+            serde_json::ser::to_string(&playground.resources).or(Err(Error::Serializing))?;
+        let translations =
+            serde_json::ser::to_string(&playground.translations).or(Err(Error::Serializing))?;
+        let locale = Some(playground.setup.locale.clone());
+
+        let mut hasher = Sha256::new();
+        for part in &[
+            &playground.messages,
+            &resources,
+            &translations,
+            &variables,
+            &setup,
+        ] {
+            hasher.input(part.as_bytes());
+            hasher.input(b"\0");
+        }
+        let content_hash = format!("{:x}", hasher.result());
+        let size = (playground.messages.len()
+            + resources.len()
+            + translations.len()
+            + variables.len()
+            + setup.len()) as i64;
+
+        Ok(Record {
+            messages: playground.messages.clone(),
+            resources,
+            translations,
+            variables,
+            setup,
+            locale,
+            size,
+            content_hash,
+        })
+    }
+}
+
+fn now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs() as i64)
+        .unwrap_or(0)
+}
+
+/// The columns selected by `PLAYGROUND_COLUMNS`, as read from the database.
+struct Columns {
+    id: String,
+    messages: String,
+    resources: String,
+    translations: String,
+    variables: String,
+    setup: String,
+    schema_version: i64,
+}
+
+/// The columns of either table which make up a playground, after its id.
+const PLAYGROUND_COLUMNS: &str =
+    "messages, resources, translations, variables, setup, schema_version";
+
+fn read_row(row: &Row<'_>) -> rusqlite::Result<Columns> {
+    Ok(Columns {
+        id: row.get(0)?,
+        messages: row.get(1)?,
+        resources: row.get(2)?,
+        translations: row.get(3)?,
+        variables: row.get(4)?,
+        setup: row.get(5)?,
+        schema_version: row.get(6)?,
+    })
+}
+
+fn try_playground(columns: Columns) -> Result<Playground, Error> {
+    let mut setup = serde_json::from_str(&columns.setup).or(Err(Error::Deserializing))?;
+    let mut variables = serde_json::from_str(&columns.variables).or(Err(Error::Deserializing))?;
+    migrations::migrate(columns.schema_version as u64, &mut setup, &mut variables);
+    Ok(Playground {
+        id: Some(columns.id),
+        filename: None,
+        messages: columns.messages,
+        resources: serde_json::from_str(&columns.resources).or(Err(Error::Deserializing))?,
+        translations: serde_json::from_str(&columns.translations).or(Err(Error::Deserializing))?,
+        variables: load_variables(variables)?,
+        setup: load_setup(setup)?,
+    })
+}
+
+fn insert_revision(
+    conn: &Connection,
+    id: &str,
+    record: &Record,
+    time: i64,
+) -> rusqlite::Result<usize> {
+    conn.execute(
+        "INSERT INTO revisions
+            (playground_id, revision, messages, resources, translations, variables, setup,
+                schema_version, created_at)
+            SELECT ?1, COALESCE(MAX(revision), 0) + 1, ?2, ?3, ?4, ?5, ?6, ?7, ?8
+            FROM revisions WHERE playground_id = ?1",
+        params![
+            id,
+            record.messages,
+            record.resources,
+            record.translations,
+            record.variables,
+            record.setup,
+            SCHEMA_VERSION as i64,
+            time
+        ],
+    )
+}
+
+fn insert(conn: &mut Connection, id: &str, record: &Record) -> rusqlite::Result<()> {
+    let tx = conn.transaction()?;
+    let time = now();
+    tx.execute(
+        "INSERT INTO playgrounds
+            (id, messages, resources, translations, variables, setup, schema_version, locale,
+                created_at, updated_at, size, content_hash)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?9, ?10, ?11)",
+        params![
+            id,
+            record.messages,
+            record.resources,
+            record.translations,
+            record.variables,
+            record.setup,
+            SCHEMA_VERSION as i64,
+            record.locale,
+            time,
+            record.size,
+            record.content_hash
+        ],
+    )?;
+    insert_revision(&tx, id, record, time)?;
+    tx.commit()
+}
+
+/// Update a playground in place, returning the number of playgrounds changed.
+fn replace(conn: &mut Connection, id: &str, record: &Record) -> rusqlite::Result<usize> {
+    let tx = conn.transaction()?;
+    let time = now();
+    let changed = tx.execute(
+        "UPDATE playgrounds
+            SET messages = ?2, resources = ?3, translations = ?4, variables = ?5, setup = ?6,
+                schema_version = ?7, locale = ?8, updated_at = ?9, size = ?10, content_hash = ?11
+            WHERE id = ?1",
+        params![
+            id,
+            record.messages,
+            record.resources,
+            record.translations,
+            record.variables,
+            record.setup,
+            SCHEMA_VERSION as i64,
+            record.locale,
+            time,
+            record.size,
+            record.content_hash
+        ],
+    )?;
+    if changed > 0 {
+        insert_revision(&tx, id, record, time)?;
+    }
+    tx.commit()?;
+    Ok(changed)
+}
+
+/// Delete a playground and its history, returning the number of playgrounds
+/// deleted.
+fn remove(conn: &mut Connection, id: &str) -> rusqlite::Result<usize> {
+    let tx = conn.transaction()?;
+    let changed = tx.execute("DELETE FROM playgrounds WHERE id = ?1", params![id])?;
+    tx.execute(
+        "DELETE FROM revisions WHERE playground_id = ?1",
+        params![id],
+    )?;
+    tx.commit()?;
+    Ok(changed)
+}
+
+fn exists(conn: &Connection, id: &str) -> rusqlite::Result<bool> {
+    conn.query_row(
+        "SELECT COUNT(*) FROM playgrounds WHERE id = ?1",
+        params![id],
+        |row| row.get::<_, i64>(0),
+    )
+    .map(|count| count > 0)
+}
+
+impl PlaygroundStore for SqliteStore {
+    fn get(&self, id: &str) -> Result<Playground, Error> {
+        if !is_valid_id(id) {
+            return Err(Error::NotFound);
+        }
+        let conn = self.conn.lock().or(Err(Error::Fetching))?;
+        let row = conn
+            .query_row(
+                &format!(
+                    "SELECT id, {} FROM playgrounds WHERE id = ?1",
+                    PLAYGROUND_COLUMNS
+                ),
+                params![id],
+                read_row,
+            )
+            .optional()
+            .or(Err(Error::Fetching))?;
+        match row {
+            Some(row) => try_playground(row),
+            None => Err(Error::NotFound),
+        }
+    }
+
+    fn create(&self, playground: Playground) -> Result<Playground, Error> {
+        let record = Record::try_from_playground(&playground)?;
+        let id = generate_id();
+        let mut conn = self.conn.lock().or(Err(Error::Creating))?;
+        insert(&mut conn, &id, &record).or(Err(Error::Creating))?;
+        Ok(Playground {
+            id: Some(id),
+            ..playground
+        })
+    }
+
+    fn update(&self, id: &str, playground: Playground) -> Result<Playground, Error> {
+        if !is_valid_id(id) {
+            return Err(Error::NotFound);
+        }
+        let record = Record::try_from_playground(&playground)?;
+        let mut conn = self.conn.lock().or(Err(Error::Updating))?;
+        if replace(&mut conn, id, &record).or(Err(Error::Updating))? == 0 {
+            return Err(Error::NotFound);
+        }
+        Ok(Playground {
+            id: Some(id.to_string()),
+            ..playground
+        })
+    }
+
+    fn delete(&self, id: &str) -> Result<(), Error> {
+        if !is_valid_id(id) {
+            return Err(Error::NotFound);
+        }
+        let mut conn = self.conn.lock().or(Err(Error::Deleting))?;
+        if remove(&mut conn, id).or(Err(Error::Deleting))? == 0 {
+            return Err(Error::NotFound);
+        }
+        Ok(())
+    }
+
+    fn revisions(&self, id: &str) -> Result<Vec<Revision>, Error> {
+        if !is_valid_id(id) {
+            return Err(Error::NotFound);
+        }
+        let conn = self.conn.lock().or(Err(Error::Fetching))?;
+        if !exists(&conn, id).or(Err(Error::Fetching))? {
+            return Err(Error::NotFound);
+        }
+        let mut stmt = conn
+            .prepare(
+                "SELECT revision, created_at FROM revisions
+                    WHERE playground_id = ?1 ORDER BY revision DESC",
+            )
+            .or(Err(Error::Fetching))?;
+        let rows = stmt
+            .query_map(params![id], |row| {
+                Ok((row.get::<_, i64>(0)?, row.get::<_, i64>(1)?))
+            })
+            .and_then(|rows| rows.collect::<rusqlite::Result<Vec<_>>>())
+            .or(Err(Error::Fetching))?;
+        Ok(rows
+            .into_iter()
+            .map(|(rev, created_at)| Revision {
+                id: rev.to_string(),
+                created_at: format_time(UNIX_EPOCH + Duration::from_secs(created_at as u64)),
+            })
+            .collect())
+    }
+
+    fn revision(&self, id: &str, rev: &str) -> Result<Playground, Error> {
+        if !is_valid_id(id) {
+            return Err(Error::NotFound);
+        }
+        let rev: i64 = rev.parse().or(Err(Error::RevisionNotFound))?;
+        let conn = self.conn.lock().or(Err(Error::Fetching))?;
+        if !exists(&conn, id).or(Err(Error::Fetching))? {
+            return Err(Error::NotFound);
+        }
+        let row = conn
+            .query_row(
+                &format!(
+                    "SELECT playground_id, {} FROM revisions
+                        WHERE playground_id = ?1 AND revision = ?2",
+                    PLAYGROUND_COLUMNS
+                ),
+                params![id, rev],
+                read_row,
+            )
+            .optional()
+            .or(Err(Error::Fetching))?;
+        match row {
+            Some(row) => try_playground(row),
+            None => Err(Error::RevisionNotFound),
+        }
+    }
+}
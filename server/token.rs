@@ -0,0 +1,87 @@
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+
+type HmacSha256 = Hmac<Sha256>;
+
+fn mac(secret: &str, id: &str) -> HmacSha256 {
+    let mut mac = HmacSha256::new_varkey(secret.as_bytes()).expect("HMAC accepts keys of any size");
+    mac.input(id.as_bytes());
+    mac
+}
+
+fn decode_hex(hex: &str) -> Option<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+/// Issue the edit token for a playground.
+///
+/// Tokens are derived from the id and the server's secret, so they work the
+/// same for every store and don't need to be kept anywhere.
+pub fn issue(secret: &str, id: &str) -> String {
+    mac(secret, id)
+        .result()
+        .code()
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+/// Check an edit token in constant time.
+pub fn verify(secret: &str, id: &str, token: &str) -> bool {
+    match decode_hex(token) {
+        Some(code) => mac(secret, id).verify(&code).is_ok(),
+        None => false,
+    }
+}
+
+/// Compare two secrets in constant time.
+pub fn eq(a: &str, b: &str) -> bool {
+    a.len() == b.len()
+        && a.bytes()
+            .zip(b.bytes())
+            .fold(0, |diff, (x, y)| diff | (x ^ y))
+            == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn issues_hex_tokens_per_id() {
+        let token = issue("secret", "abc");
+        assert_eq!(token.len(), 64);
+        assert!(token.chars().all(|c| c.is_ascii_hexdigit()));
+        assert_eq!(token, issue("secret", "abc"));
+        assert_ne!(token, issue("secret", "abd"));
+        assert_ne!(token, issue("other", "abc"));
+    }
+
+    #[test]
+    fn verifies_only_the_issued_token() {
+        let token = issue("secret", "abc");
+        assert!(verify("secret", "abc", &token));
+        assert!(verify("secret", "abc", &token.to_uppercase()));
+        assert!(!verify("secret", "abd", &token));
+        assert!(!verify("other", "abc", &token));
+        assert!(!verify("secret", "abc", &token[..62]));
+        assert!(!verify("secret", "abc", &token[..63]));
+        assert!(!verify("secret", "abc", ""));
+        assert!(!verify("secret", "abc", &token.replace(|_| true, "z")));
+    }
+
+    #[test]
+    fn compares_secrets() {
+        assert!(eq("admin", "admin"));
+        assert!(eq("", ""));
+        assert!(!eq("admin", "Admin"));
+        assert!(!eq("admin", "admin2"));
+        assert!(!eq("admin", ""));
+    }
+}
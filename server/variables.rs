@@ -0,0 +1,305 @@
+use chrono::{DateTime, NaiveDateTime};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::convert::TryFrom;
+
+/// A playground's variables, passed to Fluent as arguments, by name.
+///
+/// Besides the typed form written by this server, plain JSON strings and
+/// numbers are accepted, the way older clients sent them.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(try_from = "serde_json::Value")]
+pub struct Variables(pub BTreeMap<String, Variable>);
+
+/// The value of a single variable.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase", deny_unknown_fields)]
+pub enum Variable {
+    String {
+        value: String,
+    },
+    /// A number, formatted as if passed through `NUMBER()` with `options`.
+    Number {
+        value: f64,
+        #[serde(default, skip_serializing_if = "NumberOptions::is_empty")]
+        options: NumberOptions,
+    },
+    /// An RFC 3339 timestamp, formatted as if passed through `DATETIME()`
+    /// with `options`.
+    Date {
+        value: String,
+        #[serde(default, skip_serializing_if = "DateTimeOptions::is_empty")]
+        options: DateTimeOptions,
+    },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NumberStyle {
+    Decimal,
+    Currency,
+    Percent,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CurrencyDisplay {
+    Symbol,
+    Code,
+    Name,
+}
+
+/// The options of Fluent's `NUMBER()`, named as in FTL.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct NumberOptions {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub style: Option<NumberStyle>,
+    /// An ISO 4217 currency code, required by the `currency` style.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub currency: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub currency_display: Option<CurrencyDisplay>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub use_grouping: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub minimum_integer_digits: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub minimum_fraction_digits: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub maximum_fraction_digits: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub minimum_significant_digits: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub maximum_significant_digits: Option<u32>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DateTimeStyle {
+    Full,
+    Long,
+    Medium,
+    Short,
+}
+
+/// How wide a textual part of a date is.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TextWidth {
+    Narrow,
+    Short,
+    Long,
+}
+
+/// How a numeric part of a date is written.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NumericWidth {
+    Numeric,
+    #[serde(rename = "2-digit")]
+    TwoDigit,
+}
+
+/// Months can be written either way.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MonthWidth {
+    Numeric,
+    #[serde(rename = "2-digit")]
+    TwoDigit,
+    Narrow,
+    Short,
+    Long,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TimeZoneName {
+    Short,
+    Long,
+}
+
+/// The options of Fluent's `DATETIME()`, named as in FTL.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct DateTimeOptions {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub date_style: Option<DateTimeStyle>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub time_style: Option<DateTimeStyle>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub weekday: Option<TextWidth>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub era: Option<TextWidth>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub year: Option<NumericWidth>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub month: Option<MonthWidth>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub day: Option<NumericWidth>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hour: Option<NumericWidth>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub minute: Option<NumericWidth>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub second: Option<NumericWidth>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hour12: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub time_zone: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub time_zone_name: Option<TimeZoneName>,
+}
+
+impl NumberOptions {
+    fn is_empty(&self) -> bool {
+        *self == NumberOptions::default()
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        match self.currency {
+            Some(ref currency)
+                if currency.len() != 3 || !currency.chars().all(|c| c.is_ascii_uppercase()) =>
+            {
+                return Err(format!(
+                    "options.currency must be an ISO 4217 code, not {:?}",
+                    currency
+                ));
+            }
+            None if self.style == Some(NumberStyle::Currency) => {
+                return Err("options.currency is required by the currency style".to_string());
+            }
+            _ => {}
+        }
+        check_digits("minimumIntegerDigits", self.minimum_integer_digits, 1, 21)?;
+        check_digit_range(
+            "FractionDigits",
+            self.minimum_fraction_digits,
+            self.maximum_fraction_digits,
+            0,
+            20,
+        )?;
+        check_digit_range(
+            "SignificantDigits",
+            self.minimum_significant_digits,
+            self.maximum_significant_digits,
+            1,
+            21,
+        )
+    }
+}
+
+fn check_digits(name: &str, digits: Option<u32>, min: u32, max: u32) -> Result<(), String> {
+    match digits {
+        Some(digits) if digits < min || digits > max => Err(format!(
+            "options.{} must be between {} and {}",
+            name, min, max
+        )),
+        _ => Ok(()),
+    }
+}
+
+/// Check a `minimum…`/`maximum…` pair of options.
+fn check_digit_range(
+    suffix: &str,
+    minimum: Option<u32>,
+    maximum: Option<u32>,
+    min: u32,
+    max: u32,
+) -> Result<(), String> {
+    check_digits(&format!("minimum{}", suffix), minimum, min, max)?;
+    check_digits(&format!("maximum{}", suffix), maximum, min, max)?;
+    match (minimum, maximum) {
+        (Some(minimum), Some(maximum)) if minimum > maximum => Err(format!(
+            "options.minimum{} must not be greater than options.maximum{}",
+            suffix, suffix
+        )),
+        _ => Ok(()),
+    }
+}
+
+impl DateTimeOptions {
+    fn is_empty(&self) -> bool {
+        *self == DateTimeOptions::default()
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        match self.time_zone {
+            Some(ref time_zone) if time_zone.trim().is_empty() => {
+                Err("options.timeZone must not be empty".to_string())
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Whether a string looks like the output of JavaScript's
+/// `Date.prototype.toISOString`, which the client reads as a date.
+fn is_iso_date(value: &str) -> bool {
+    value.len() == 24 && NaiveDateTime::parse_from_str(value, "%Y-%m-%dT%H:%M:%S%.3fZ").is_ok()
+}
+
+impl Variable {
+    /// Read a variable from the plain JSON sent by older clients.
+    fn from_plain(value: serde_json::Value) -> Result<Self, String> {
+        match value {
+            serde_json::Value::String(value) => {
+                if is_iso_date(&value) {
+                    Ok(Variable::Date {
+                        value,
+                        options: DateTimeOptions::default(),
+                    })
+                } else {
+                    Ok(Variable::String { value })
+                }
+            }
+            serde_json::Value::Number(number) => Ok(Variable::Number {
+                value: number.as_f64().unwrap_or_default(),
+                options: NumberOptions::default(),
+            }),
+            serde_json::Value::Object(_) => {
+                serde_json::from_value(value).map_err(|err| err.to_string())
+            }
+            _ => Err("must be a string, a number or a typed variable".to_string()),
+        }
+    }
+
+    /// Read and check a variable, either plain or typed.
+    pub fn read(value: serde_json::Value) -> Result<Self, String> {
+        let variable = Variable::from_plain(value)?;
+        variable.validate()?;
+        Ok(variable)
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        match self {
+            Variable::String { .. } => Ok(()),
+            Variable::Number { options, .. } => options.validate(),
+            Variable::Date { value, options } => {
+                if DateTime::parse_from_rfc3339(value).is_err() {
+                    return Err(format!("value must be an RFC 3339 date, not {:?}", value));
+                }
+                options.validate()
+            }
+        }
+    }
+}
+
+impl TryFrom<serde_json::Value> for Variables {
+    type Error = String;
+
+    fn try_from(value: serde_json::Value) -> Result<Self, Self::Error> {
+        let fields = match value {
+            serde_json::Value::Object(fields) => fields,
+            _ => return Err("variables must be an object".to_string()),
+        };
+        let mut variables = BTreeMap::new();
+        for (name, value) in fields {
+            let variable =
+                Variable::read(value).map_err(|err| format!("variables.{}: {}", name, err))?;
+            variables.insert(name, variable);
+        }
+        Ok(Variables(variables))
+    }
+}